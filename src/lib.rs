@@ -0,0 +1,104 @@
+//! A stable two stage merge sort with pre-sorted prefix optimization.
+//!
+//! The two stages are:
+//!
+//! 1) Top-down recursive depth-first merge, which helps data locality
+//! 2) Small slices sort using a fast insertion sort, then merge
+//!
+//! [`sort`], [`sort_by`], [`sort_by_key`] and [`sort_by_cached_key`] are the sequential entry
+//! points, and [`par_sort`], [`par_sort_by`] and [`par_sort_by_key`] the parallel ones (with the
+//! default `rayon` feature). The same sorts are available as slice methods through
+//! [`NewSortExt`] and [`ParNewSortExt`], [`sort_pairs`] sorts parallel key and value slices, and
+//! [`sort_primitive`] sorts integers and floats with sorting networks for small slices, and
+//! [`sort_copy`] sorts small `Copy` elements with a branchless merge. [`sort_adaptive`] merges
+//! the natural runs of a slice, for nearly sorted data with scattered out of place elements.
+//! [`sort_with_config`] and [`par_sort_with_config`] take a [`SortConfig`] to override the slice
+//! lengths the sort was tuned with. The benchmarked variants of the merge step are available as
+//! their own modules.
+//!
+//! The sequential sorts are defined in the [`newsort`] module, which has the same name as the
+//! crate. A glob import `use newsort::*;` brings the module into scope alongside the crate, and
+//! any later `newsort::` path is then ambiguous (error E0659). Import the items you use by name
+//! instead, or write the crate path as `::newsort`:
+//!
+//! ```
+//! use newsort::sort_by;
+//!
+//! let mut v = [3, 1, 2];
+//! sort_by(&mut v, |a, b| b.cmp(a));
+//! ::newsort::newsort::sort(&mut v);
+//! assert!(v == [1, 2, 3]);
+//! ```
+//!
+//! # Features
+//!
+//! The crate is `no_std`. The default `alloc` feature enables the buffered merge sort, which
+//! allocates temporary storage half the size of the slice. Without it, [`sort`] and friends
+//! merge in place as by [`sort_in_place`], using only `core`. The default `rayon` feature
+//! enables the parallel sorts, and requires `std`. The `bench` feature only adds the benchmark
+//! data generator's dependencies, for the `newsort` and `newsort-tune` binaries.
+//!
+//! # Examples
+//!
+//! ```
+//! let mut v = [-5, 4, 1, -3, 2];
+//!
+//! newsort::sort(&mut v);
+//! assert!(v == [-5, -3, 1, 2, 4]);
+//! ```
+
+#![no_std]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "rayon")]
+extern crate std;
+
+pub mod newsort;
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub mod pairs;
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub mod primitive;
+#[cfg(feature = "rayon")]
+pub mod par_newsort;
+pub mod slice_ext;
+mod tuned;
+
+/// Variant of [`newsort`] which binary searches for the start of the merge.
+#[cfg(feature = "alloc")]
+pub mod newsort_bin;
+/// Variant of [`newsort_bin`] which moves the first element into place while searching.
+#[cfg(feature = "alloc")]
+pub mod newsort_bin2;
+/// Snapshot of [`newsort`] as benchmarked in `ressw2/`.
+#[cfg(feature = "alloc")]
+pub mod ssf_swap_large;
+
+pub use crate::newsort::{
+    merge_adjacent, merge_adjacent_by, sort, sort_by, sort_by_key, sort_by_with_buffer,
+    sort_in_place, sort_in_place_by, sort_with_buffer, sort_with_sorted_prefix,
+    sort_with_sorted_prefix_by, SortConfig,
+};
+#[cfg(feature = "alloc")]
+pub use crate::newsort::{
+    argsort, argsort_by, argsort_by_u32, argsort_u32, extend_sorted_tail, extend_sorted_tail_by,
+    merge_many, merge_many_by, merge_sorted, merge_sorted_by, partial_sort, partial_sort_by,
+    select_top_k_stable, select_top_k_stable_by, sort_adaptive, sort_adaptive_by,
+    sort_by_cached_key, sort_by_with_config, sort_by_with_stats, sort_copy, sort_copy_by,
+    sort_dedup, sort_dedup_by_key, sort_dedup_counts, sort_dedup_counts_by_key, sort_with_config,
+    sort_with_stats, try_sort, try_sort_by, SortScratch, SortStats,
+};
+#[cfg(feature = "allocator-api2")]
+pub use crate::newsort::{sort_by_in, sort_in};
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub use crate::pairs::{sort_pairs, sort_pairs_by};
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub use crate::primitive::{sort_primitive, Primitive};
+#[cfg(feature = "rayon")]
+pub use crate::par_newsort::{
+    par_sort, par_sort_by, par_sort_by_key, par_sort_by_with_config, par_sort_with_config,
+};
+pub use crate::slice_ext::NewSortExt;
+#[cfg(feature = "rayon")]
+pub use crate::slice_ext::ParNewSortExt;
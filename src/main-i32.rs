@@ -14,9 +14,10 @@
 //     * add parallel sort option
 //     * call stdsort from a module or from stdlib
 
-mod newsort;
+mod patterns;
 mod stdsort;
-mod par_newsort;
+use patterns::{generate_int, Pattern, TestType, Variant};
+use ::newsort::{newsort, par_newsort};
 use std::cmp::Ordering;
 
 #[derive(Debug,Clone,Copy,Eq)]
@@ -48,6 +49,9 @@ where
         Algorithm::Std => stdsort::sort_by(&mut v_std, |a, b| { cnt_std += 1; a.cmp(b) }),
         Algorithm::LibStd => v_std.sort_by(|a, b| { cnt_std += 1; a.cmp(b) }),
         Algorithm::Newsort => newsort::sort_by(&mut v_std, |a, b| { cnt_std += 1; a.cmp(b) }),
+        Algorithm::NewsortInPlace => newsort::sort_in_place_by(&mut v_std, |a, b| { cnt_std += 1; a.cmp(b) }),
+        Algorithm::NewsortBranchless => newsort::sort_copy_by(&mut v_std, |a, b| { cnt_std += 1; a.cmp(b) }),
+        Algorithm::NewsortAdaptive => newsort::sort_adaptive_by(&mut v_std, |a, b| { cnt_std += 1; a.cmp(b) }),
         Algorithm::ParStd => v_std.par_sort_by(|a, b| a.cmp(b)),
         Algorithm::ParNewsort => par_newsort::par_sort_by(&mut v_std, |a, b| a.cmp(b)),
     }
@@ -55,6 +59,9 @@ where
         Algorithm::Std => stdsort::sort_by(&mut v_ns, |a, b| { cnt_ns += 1; a.cmp(b) }),
         Algorithm::LibStd => v_ns.sort_by(|a, b| { cnt_ns += 1; a.cmp(b) }),
         Algorithm::Newsort => newsort::sort_by(&mut v_ns, |a, b| { cnt_ns += 1; a.cmp(b) }),
+        Algorithm::NewsortInPlace => newsort::sort_in_place_by(&mut v_ns, |a, b| { cnt_ns += 1; a.cmp(b) }),
+        Algorithm::NewsortBranchless => newsort::sort_copy_by(&mut v_ns, |a, b| { cnt_ns += 1; a.cmp(b) }),
+        Algorithm::NewsortAdaptive => newsort::sort_adaptive_by(&mut v_ns, |a, b| { cnt_ns += 1; a.cmp(b) }),
         Algorithm::ParStd => v_ns.par_sort_by(|a, b| a.cmp(b)),
         Algorithm::ParNewsort => par_newsort::par_sort_by(&mut v_ns, |a, b| a.cmp(b)),
     }
@@ -78,97 +85,19 @@ where
     (cnt_std, cnt_ns, failed)
 }
 
-use std::cmp::min;
-use std::fmt::{self, Display, Formatter};
 
 #[derive(Copy,Clone,Debug)]
 enum Algorithm {
     Std,
     LibStd,
     Newsort,
+    NewsortInPlace,
+    NewsortBranchless,
+    NewsortAdaptive,
     ParStd,
     ParNewsort,
 }
 
-#[derive(Copy,Clone)]
-enum Pattern {
-    Sawtooth,
-    Rand,
-    Stagger,
-    Plateau,
-    Shuffle,
-}
-
-impl Display for Pattern {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match *self {
-            Pattern::Sawtooth => "sawtooth",
-            Pattern::Rand => "rand",
-            Pattern::Stagger => "stagger",
-            Pattern::Plateau => "plateau",
-            Pattern::Shuffle => "shuffle",
-        }.fmt(f)
-    }
-}
-
-#[derive(Copy,Clone)]
-enum Variant {
-    Ident,
-    Reverse,
-    ReverseFront,
-    ReverseBack,
-    Sorted,
-    ReverseSorted,
-    Dither,
-}
-
-impl Display for Variant {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match *self {
-            Variant::Ident => "ident",
-            Variant::Reverse => "reverse",
-            Variant::ReverseFront => "reverse_front",
-            Variant::ReverseBack => "reverse_back",
-            Variant::Sorted => "sorted",
-            Variant::ReverseSorted => "reverse_sorted",
-            Variant::Dither => "dither",
-        }.fmt(f)
-    }
-}
-
-fn generate_int(pattern: Pattern, variant: Variant, size: usize, rand_size: bool, m: usize, test_type: TestType, run: usize) -> Vec<i32> {
-    use rand::prelude::*;
-    let mut rng =
-        if let TestType::BenchMark = test_type {
-            StdRng::seed_from_u64((m*size + run) as u64)
-        } else {
-            StdRng::from_entropy()
-        };
-    let rsize = (rng.gen::<f32>()*10.0*size as f32) as usize;
-    let size = if rand_size { rsize } else { size };
-    let mut ret_val = Vec::with_capacity(size);
-    let (mut j, mut k) = (0, 0);
-    for i in 0 .. size {
-        ret_val.push(match pattern {
-            Pattern::Sawtooth => i % m,
-            Pattern::Rand => rng.gen::<usize>(),
-            Pattern::Stagger => (i*m + i) % size,
-            Pattern::Plateau => min(i, m),
-            Pattern::Shuffle => if rng.gen::<usize>() % m == 0 { j+=2; j } else { k += 2; k },
-        } as i32);
-    }
-    match variant {
-        Variant::Ident => (),
-        Variant::Reverse => ret_val.reverse(),
-        Variant::ReverseFront => ret_val[0 .. size / 2].reverse(),
-        Variant::ReverseBack => ret_val[size / 2 .. ].reverse(),
-        Variant::Sorted => ret_val.sort(),
-        Variant::ReverseSorted => { ret_val.sort(); ret_val.reverse() },
-        Variant::Dither => for x in &mut ret_val { *x %= 5; },
-    }
-    ret_val
-}
-
 fn run_test<T>(algorithm: Algorithm, v_once: &[T]) -> (f64, usize)
 where
     T: Ord + Copy + Send + Sync,
@@ -184,6 +113,9 @@ where
             Algorithm::Std => stdsort::sort(&mut v),
             Algorithm::LibStd => v.sort(),
             Algorithm::Newsort => newsort::sort(&mut v),
+            Algorithm::NewsortInPlace => newsort::sort_in_place(&mut v),
+            Algorithm::NewsortBranchless => newsort::sort_copy(&mut v),
+            Algorithm::NewsortAdaptive => newsort::sort_adaptive(&mut v),
             Algorithm::ParStd => v.par_sort(),
             Algorithm::ParNewsort => par_newsort::par_sort(&mut v),
         }
@@ -199,19 +131,13 @@ where
     ((len*trial_count) as f64/1_000_000_f64/elapsed, trial_count)
 }
 
-#[derive(Copy, Clone)]
-enum TestType {
-    BenchMark,
-    EqualityTest,
-}
-
 struct Arg<T>{
     default: T,
     value: Option<T>,
 }
 impl<T: Copy> Arg<T>{
     fn new(default: T) -> Arg<T> {
-        Self { default: default, value: None }
+        Self { default, value: None }
     }
     fn get(&self) -> T {
         if let Some(val) = self.value {
@@ -276,6 +202,9 @@ fn main() {
                         "std" => alg.set(Algorithm::Std),
                         "lib-std" => alg.set(Algorithm::LibStd),
                         "new" => alg.set(Algorithm::Newsort),
+                        "new-in-place" => alg.set(Algorithm::NewsortInPlace),
+                        "new-branchless" => alg.set(Algorithm::NewsortBranchless),
+                        "new-adaptive" => alg.set(Algorithm::NewsortAdaptive),
                         "par-std" => alg.set(Algorithm::ParStd),
                         "par-new" => alg.set(Algorithm::ParNewsort),
                         _ => { test_type = Err("invalid algorithm"); break },
@@ -308,7 +237,8 @@ fn main() {
         eprintln!("error: {}", s);
         eprintln!("usage: newsort [ benchmark ] [ eq ] [ -n n | --nruns n ] [ -v | --verbose ]");
         eprintln!("               [ --max n ] [ --min n ] [ -p | --parallel ] [ --no-rand-sizes ]");
-        eprintln!("               [ --std std | new | par-std | par-new ] [ --new std | new | par-std | par-new ]");
+        eprintln!("               [ --std std | new | new-in-place | new-branchless | new-adaptive | par-std | par-new ]");
+        eprintln!("               [ --new std | new | new-in-place | new-branchless | new-adaptive | par-std | par-new ]");
         std::process::exit(1);
     }
 
@@ -320,7 +250,7 @@ fn main() {
     let strings = {
         use std::io::Read;
         let path = std::path::Path::new("./strings.txt");
-        let mut file = std::fs::File::open(&path).unwrap();
+        let mut file = std::fs::File::open(path).unwrap();
         let mut s = String::new();
         file.read_to_string(&mut s).unwrap();
         s
@@ -417,7 +347,7 @@ fn main() {
                 let m = if m == 0 { 2 } else { m };
                 for &pattern in &[Pattern::Sawtooth, Pattern::Rand, Pattern::Stagger, Pattern::Plateau, Pattern::Shuffle] {
                     for &variant in &[Variant::Ident, Variant::Reverse, Variant::ReverseFront, Variant::ReverseBack, Variant::Sorted, Variant::ReverseSorted, Variant::Dither] {
-                        let v = generate_int(pattern, variant, size, rand_sizes, m, test_type, run);
+                        let v: Vec<i32> = generate_int(pattern, variant, size, rand_sizes, m, test_type, run);
                         let (cmp_count_std, cmp_count_ns, failed) = test_sort_eq(alg_std, alg_ns, &v);
                         if failed {
                             println!("{: >7} / {: >7} {: >7}{: >15}{: >15}",
@@ -1,13 +1,17 @@
 // simple-forward-large-slice-swap
 
-#[cfg(not(no_global_oom_handling))]
 use core::cmp::Ordering::{self, Less};
-#[cfg(not(no_global_oom_handling))]
-use core::mem;
-#[cfg(not(no_global_oom_handling))]
-use core::mem::size_of;
-#[cfg(not(no_global_oom_handling))]
+use core::mem::{align_of, size_of, MaybeUninit};
 use core::ptr;
+use core::slice;
+
+use crate::tuned::TUNED;
+
+#[cfg(feature = "alloc")]
+use alloc::{collections::TryReserveError, vec::Vec};
+
+#[cfg(feature = "allocator-api2")]
+use allocator_api2::{alloc::Allocator, vec::Vec as AllocVec};
 
 /// Sorts the slice.
 ///
@@ -25,14 +29,15 @@ use core::ptr;
 /// randomly sorted sequences.
 ///
 /// Also, it allocates temporary storage half the size of `self`, but for short slices a
-/// non-allocating insertion sort is used instead.
+/// non-allocating insertion sort is used instead. Without the `alloc` feature nothing is
+/// allocated, and the slice is sorted as by [`sort_in_place`].
 ///
 /// # Examples
 ///
 /// ```
 /// let mut v = [-5, 4, 1, -3, 2];
 ///
-/// v.sort();
+/// newsort::newsort::sort(&mut v);
 /// assert!(v == [-5, -3, 1, 2, 4]);
 /// ```
 #[cfg(not(no_global_oom_handling))]
@@ -42,7 +47,7 @@ pub fn sort<T>(v: &mut [T])
 where
     T: Ord,
 {
-    merge_sort(v, |a, b| a.lt(b));
+    merge_sort(v, |a, b| a.lt(b), &mut ());
 }
 
 /// Sorts the slice with a comparator function.
@@ -61,7 +66,7 @@ where
 ///
 /// ```
 /// let mut floats = [5f64, 4.0, 1.0, 3.0, 2.0];
-/// floats.sort_by(|a, b| a.partial_cmp(b).unwrap());
+/// newsort::newsort::sort_by(&mut floats, |a, b| a.partial_cmp(b).unwrap());
 /// assert_eq!(floats, [1.0, 2.0, 3.0, 4.0, 5.0]);
 /// ```
 ///
@@ -83,11 +88,11 @@ where
 ///
 /// ```
 /// let mut v = [5, 4, 1, 3, 2];
-/// v.sort_by(|a, b| a.cmp(b));
+/// newsort::newsort::sort_by(&mut v, |a, b| a.cmp(b));
 /// assert!(v == [1, 2, 3, 4, 5]);
 ///
 /// // reverse sorting
-/// v.sort_by(|a, b| b.cmp(a));
+/// newsort::newsort::sort_by(&mut v, |a, b| b.cmp(a));
 /// assert!(v == [5, 4, 3, 2, 1]);
 /// ```
 #[cfg(not(no_global_oom_handling))]
@@ -97,343 +102,3611 @@ pub fn sort_by<T, F>(v: &mut[T], mut compare: F)
 where
     F: FnMut(&T, &T) -> Ordering,
 {
-    merge_sort(v, |a, b| compare(a, b) == Less);
+    merge_sort(v, |a, b| compare(a, b) == Less, &mut ());
 }
 
-////////////////////////////////////////////////////////////////////////////////
-// Sorting
-////////////////////////////////////////////////////////////////////////////////
-
-/// Inserts `v[v.len() - 1]` into pre-sorted sequence `v[..v.len() - 1]` so that whole `v[..]` becomes sorted.
+/// Sorts the slice with a key extraction function.
 ///
-/// This is the integral subroutine of insertion sort.
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*m* \* *n* \* log(*n*))
+/// worst-case, where the key function is *O*(*m*).
+///
+/// For expensive key functions (e.g. functions that are not simple property accesses or
+/// basic operations), [`sort_by_cached_key`] is likely to be
+/// significantly faster, as it does not recompute element keys.
+///
+/// When applicable, unstable sorting is preferred because it is generally faster than stable
+/// sorting and it doesn't allocate auxiliary memory.
+/// See [`sort_unstable_by_key`](slice::sort_unstable_by_key).
+///
+/// # Current implementation
+///
+/// This is a stable two stage merge sort with pre-sorted prefix optimization.
+/// It is quite fast in cases where the slice is nearly sorted, or consists of
+/// two or more sorted sequences concatenated one after another, while remaining very fast for
+/// randomly sorted sequences.
+///
+/// Also, it allocates temporary storage half the size of `self`, but for short slices a
+/// non-allocating insertion sort is used instead.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [-5i32, 4, 1, -3, 2];
+///
+/// newsort::newsort::sort_by_key(&mut v, |k| k.abs());
+/// assert!(v == [1, 2, -3, 4, -5]);
+/// ```
 #[cfg(not(no_global_oom_handling))]
-// benchmarking indicated that inlining makes a substantial improvement, yet only requires a couple of hundred bytes
-#[inline(always)]
-fn insert_end<T, F>(v: &mut [T], is_less: &mut F)
+//#[stable(feature = "slice_sort_by_key", since = "1.7.0")]
+#[inline]
+pub fn sort_by_key<T, K, F>(v: &mut [T], mut f: F)
 where
-    F: FnMut(&T, &T) -> bool,
+    F: FnMut(&T) -> K,
+    K: Ord,
 {
-    let end = v.len().saturating_sub(1);
-    if end > 0 && is_less(&v[end], &v[end - 1]) {
-        unsafe {
-            // There are three ways to implement insertion here:
-            //
-            // 1. Swap adjacent elements until the last one gets to its final destination.
-            //    However, this way we copy data around more than is necessary. If elements are big
-            //    structures (costly to copy), this method will be slow.
-            //
-            // 2. Iterate until the right place for the last element is found. Then shift the
-            //    elements preceeding it to make room for it and finally place it into the
-            //    remaining hole. This is a good method.
-            //
-            // 3. Copy the last element into a temporary variable. Iterate until the right place
-            //    for it is found. As we go along, copy every traversed element into the slot
-            //    succeeding it. Finally, copy data from the temporary variable into the remaining
-            //    hole. This method is very good. Benchmarks demonstrated slightly better
-            //    performance than with the 2nd method.
-            //
-            // All methods were benchmarked, and the 3rd showed best results. So we chose that one.
-            let tmp = mem::ManuallyDrop::new(ptr::read(v.get_unchecked(end)));
-
-            // Intermediate state of the insertion process is always tracked by `hole`, which
-            // serves two purposes:
-            // 1. Protects integrity of `v` from panics in `is_less`.
-            // 2. Fills the remaining hole in `v` in the end.
-            //
-            // Panic safety:
-            //
-            // If `is_less` panics at any point during the process, `hole` will get dropped and
-            // fill the hole in `v` with `tmp`, thus ensuring that `v` still holds every object it
-            // initially held exactly once.
-            let mut hole = InsertionHole { src: &*tmp, dest: v.get_unchecked_mut(end - 1) };
-            ptr::copy_nonoverlapping(hole.dest, v.get_unchecked_mut(end), 1);
-
-            let mut i = end - 1;
-            while i > 0 && is_less(&*tmp, v.get_unchecked(i - 1)) {
-                hole.dest = v.get_unchecked_mut(i - 1);
-                ptr::copy_nonoverlapping(hole.dest, v.get_unchecked_mut(i), 1);
-                i -= 1;
-            }
-            // `hole` gets dropped and thus copies `tmp` into the remaining hole in `v`.
-        }
-    }
-
-    // When dropped, copies from `src` into `dest`.
-    struct InsertionHole<T> {
-        src: *const T,
-        dest: *mut T,
-    }
-
-    impl<T> Drop for InsertionHole<T> {
-        fn drop(&mut self) {
-            unsafe {
-                ptr::copy_nonoverlapping(self.src, self.dest, 1);
-            }
-        }
-    }
+    merge_sort(v, |a, b| f(a).lt(&f(b)), &mut ());
 }
 
-/// Merges non-decreasing runs `v[..mid]` and `v[mid..]` using `buf` as temporary storage, and
-/// stores the result into `v[..]`.
+/// Sorts the slice with a key extraction function.
 ///
-/// # Safety
+/// During sorting, the key function is called only once per element.
 ///
-/// The two slices must be non-empty and `mid` must be in bounds. Buffer `buf` must be long enough
-/// to hold a copy of the shorter slice.
-#[allow(unused_unsafe)]
-#[cfg(not(no_global_oom_handling))]
-unsafe fn merge<T, F>(v: &mut [T], mid: usize, buf: *mut T, is_less: &mut F)
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*m* \* *n* + *n* \* log(*n*))
+/// worst-case, where the key function is *O*(*m*).
+///
+/// For simple key functions (e.g., functions that are property accesses or
+/// basic operations), [`sort_by_key`] is likely to be
+/// faster.
+///
+/// # Current implementation
+///
+/// The keys are collected into a `Vec<(K, index)>` the length of the slice, which is then sorted
+/// with the same two stage merge sort as [`sort`], so key-sorted and reverse key-sorted input
+/// benefits from the pre-sorted prefix optimization. The smallest index type that can address
+/// the slice is used, to reduce allocation. Finally the slice is permuted into place.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [-5i32, 4, 32, -3, 2];
+///
+/// newsort::newsort::sort_by_cached_key(&mut v, |k| k.to_string());
+/// assert!(v == [-3, -5, 2, 32, 4]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+//#[stable(feature = "slice_sort_by_cached_key", since = "1.34.0")]
+#[inline]
+pub fn sort_by_cached_key<T, K, F>(v: &mut [T], f: F)
 where
-    F: FnMut(&T, &T) -> bool,
+    F: FnMut(&T) -> K,
+    K: Ord,
 {
-    let len = v.len();
-    let v = v.as_mut_ptr();
-    let (v_mid, v_end) = unsafe { (v.add(mid), v.add(len)) };
-
-    // The merge process first copies the shorter run into `buf`. Then it traces the newly copied
-    // run and the longer run forwards (or backwards), comparing their next unconsumed elements and
-    // copying the lesser (or greater) one into `v`.
-    //
-    // As soon as the shorter run is fully consumed, the process is done. If the longer run gets
-    // consumed first, then we must copy whatever is left of the shorter run into the remaining
-    // hole in `v`.
-    //
-    // Intermediate state of the process is always tracked by `hole`, which serves two purposes:
-    // 1. Protects integrity of `v` from panics in `is_less`.
-    // 2. Fills the remaining hole in `v` if the longer run gets consumed first.
-    //
-    // Panic safety:
-    //
-    // If `is_less` panics at any point during the process, `hole` will get dropped and fill the
-    // hole in `v` with the unconsumed range in `buf`, thus ensuring that `v` still holds every
-    // object it initially held exactly once.
-    let mut hole;
-
-    if mid <= len - mid {
-        // The left run is shorter.
-        unsafe {
-            ptr::copy_nonoverlapping(v, buf, mid);
-            hole = MergeHole { start: buf, end: buf.add(mid), dest: v };
-        }
-
-        // Initially, these pointers point to the beginnings of their arrays.
-        let left = &mut hole.start;
-        let mut right = v_mid;
-        let out = &mut hole.dest;
-
-        while *left < hole.end && right < v_end {
-            // Consume the lesser side.
-            // If equal, prefer the left run to maintain stability.
-            unsafe {
-                let to_copy = if is_less(&*right, &**left) {
-                    get_and_increment(&mut right)
-                } else {
-                    get_and_increment(left)
-                };
-                ptr::copy_nonoverlapping(to_copy, get_and_increment(out), 1);
-            }
-        }
-    } else {
-        // The right run is shorter.
-        unsafe {
-            ptr::copy_nonoverlapping(v_mid, buf, len - mid);
-            hole = MergeHole { start: buf, end: buf.add(len - mid), dest: v_mid };
-        }
-
-        // Initially, these pointers point past the ends of their arrays.
-        let left = &mut hole.dest;
-        let right = &mut hole.end;
-        let mut out = v_end;
-
-        while v < *left && buf < *right {
-            // Consume the greater side.
-            // If equal, prefer the right run to maintain stability.
-            unsafe {
-                let to_copy = if is_less(&*right.offset(-1), &*left.offset(-1)) {
-                    decrement_and_get(left)
-                } else {
-                    decrement_and_get(right)
-                };
-                ptr::copy_nonoverlapping(to_copy, decrement_and_get(&mut out), 1);
+    // Helper macro for indexing our vector by the smallest possible type, to reduce allocation.
+    macro_rules! sort_by_key {
+        ($t:ty, $slice:ident, $f:ident) => {{
+            let mut indices: Vec<_> = $slice
+                .iter()
+                .map($f)
+                .enumerate()
+                .map(|(i, k)| (k, i as $t))
+                .collect();
+            // Only the keys are compared: `merge_sort` is stable, so equal keys keep their
+            // original index order.
+            merge_sort(&mut indices, |a, b| a.0.lt(&b.0), &mut ());
+            for i in 0..$slice.len() {
+                let mut index = indices[i].1;
+                while (index as usize) < i {
+                    index = indices[index as usize].1;
+                }
+                indices[i].1 = index;
+                $slice.swap(i, index as usize);
             }
-        }
+        }};
     }
-    // Finally, `hole` gets dropped. If the shorter run was not fully consumed, whatever remains of
-    // it will now be copied into the hole in `v`.
 
-    #[inline(always)]
-    unsafe fn get_and_increment<T>(ptr: &mut *mut T) -> *mut T {
-        let old = *ptr;
-        *ptr = unsafe { ptr.offset(1) };
-        old
-    }
+    let sz_u8 = size_of::<(K, u8)>();
+    let sz_u16 = size_of::<(K, u16)>();
+    let sz_u32 = size_of::<(K, u32)>();
+    let sz_usize = size_of::<(K, usize)>();
 
-    #[inline(always)]
-    unsafe fn decrement_and_get<T>(ptr: &mut *mut T) -> *mut T {
-        *ptr = unsafe { ptr.offset(-1) };
-        *ptr
+    let len = v.len();
+    if len < 2 {
+        return;
     }
-
-    // When dropped, copies the range `start..end` into `dest..`.
-    struct MergeHole<T> {
-        start: *mut T,
-        end: *mut T,
-        dest: *mut T,
+    if sz_u8 < sz_u16 && len <= (u8::MAX as usize) {
+        return sort_by_key!(u8, v, f);
     }
-
-    impl<T> Drop for MergeHole<T> {
-        fn drop(&mut self) {
-            // `T` is not a zero-sized type, and these are pointers into a slice's elements.
-            unsafe {
-                let len = self.end.sub_ptr(self.start);
-                ptr::copy_nonoverlapping(self.start, self.dest, len);
-            }
-        }
+    if sz_u16 < sz_u32 && len <= (u16::MAX as usize) {
+        return sort_by_key!(u16, v, f);
     }
+    if sz_u32 < sz_usize && len <= (u32::MAX as usize) {
+        return sort_by_key!(u32, v, f);
+    }
+    sort_by_key!(usize, v, f)
 }
 
-/// This is a stable two stage merge sort with pre-sorted prefix optimization. The two stages are:
+/// Returns the permutation which sorts the slice, leaving the slice untouched.
 ///
-/// 1) Top-down recursive depth-first merge, which helps data locality
-/// 2) Small slices sort using a fast insertion sort, then merge
+/// `v[argsort(v)[0]]`, `v[argsort(v)[1]]`, ... are the elements of `v` in sorted order. The sort
+/// is stable: the indices of equal elements appear in ascending order, so each index is tied to
+/// its element exactly as if `(element, index)` pairs had been sorted by element alone. It is
+/// *O*(*n* \* log(*n*)) worst-case.
 ///
-/// The total running time is *O*(*n* \* log(*n*)) worst-case.
-#[cfg(not(no_global_oom_handling))]
-fn merge_sort<T, F>(v: &mut [T], mut is_less: F)
+/// See [`argsort_u32`] to halve the memory used by the permutation.
+///
+/// # Current implementation
+///
+/// An index vector is sorted with the same two stage merge sort as [`sort`], comparing the
+/// elements the indices refer to, so presorted input benefits from the pre-sorted prefix
+/// optimization.
+///
+/// # Examples
+///
+/// ```
+/// let v = [30, 10, 20, 10];
+///
+/// assert_eq!(newsort::argsort(&v), [1, 3, 2, 0]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn argsort<T>(v: &[T]) -> Vec<usize>
 where
-    F: FnMut(&T, &T) -> bool,
+    T: Ord,
 {
-    // `gt!` macro centralizes and clarifies the logic.
-    // Unchecked array access gives an approximate 20% performance improvement.
-    //
-    // # Safety
-    //
-    // `$left` and `$right` must be < `$v.len()`
-    macro_rules! gt {
-        ($v: ident, $left: expr, $right: expr, $is_less: ident) => {
-            // $is_less(&$v[$right], &$v[$left])
-            $is_less(unsafe { &$v.get_unchecked($right) }, unsafe { &$v.get_unchecked($left) })
-        };
-    }
+    argsort_by(v, |a, b| a.cmp(b))
+}
 
-    // Benchmarking determined these are the best sizes.
-    // Recursive merge switches to insertion sort / merge when slice length is <= SMALL_SLICE_LEN*2.
-    const SMALL_SLICE_LEN: usize = 10;
-    // Slices of up to this length get sorted using insertion sort.
-    const MAX_INSERTION: usize = 20;
+/// Returns the permutation which sorts the slice with a comparator function, leaving the slice
+/// untouched.
+///
+/// The permutation is stable and *O*(*n* \* log(*n*)) worst-case, see [`argsort`]. The comparator
+/// function must define a total ordering for the elements in the slice, see [`sort_by`].
+///
+/// # Examples
+///
+/// ```
+/// let v = [30, 10, 20, 10];
+///
+/// assert_eq!(newsort::argsort_by(&v, |a, b| b.cmp(a)), [0, 2, 1, 3]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub fn argsort_by<T, F>(v: &[T], mut compare: F) -> Vec<usize>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut indices: Vec<usize> = (0..v.len()).collect();
+    // Every index is < `v.len()`.
+    merge_sort(
+        &mut indices,
+        |&a, &b| unsafe { compare(v.get_unchecked(a), v.get_unchecked(b)) == Less },
+        &mut (),
+    );
+    indices
+}
 
-    // Sorting has no meaningful behavior on zero-sized types.
-    if size_of::<T>() == 0 {
-        return;
-    }
+/// Returns the permutation which sorts the slice as `u32` indices, leaving the slice untouched.
+///
+/// This is the same as [`argsort`], using half the memory on 64-bit targets.
+///
+/// # Panics
+///
+/// Panics if `v.len()` is greater than `u32::MAX`.
+///
+/// # Examples
+///
+/// ```
+/// let v = ["c", "a", "b", "a"];
+///
+/// assert_eq!(newsort::argsort_u32(&v), [1u32, 3, 2, 0]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn argsort_u32<T>(v: &[T]) -> Vec<u32>
+where
+    T: Ord,
+{
+    argsort_by_u32(v, |a, b| a.cmp(b))
+}
 
-    let len = v.len();
-    // Short arrays get sorted in-place via insertion sort to avoid allocations.
-    if len <= MAX_INSERTION {
-        for i in 1..len {
-            insert_end(&mut v[..=i], &mut is_less);
-        }
-        return;
-    }
+/// Returns the permutation which sorts the slice with a comparator function as `u32` indices,
+/// leaving the slice untouched.
+///
+/// This is the same as [`argsort_by`], using half the memory on 64-bit targets.
+///
+/// # Panics
+///
+/// Panics if `v.len()` is greater than `u32::MAX`.
+///
+/// # Examples
+///
+/// ```
+/// let v = [1.5, -0.5, 0.0];
+///
+/// assert_eq!(newsort::argsort_by_u32(&v, |a, b| a.partial_cmp(b).unwrap()), [1u32, 2, 0]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub fn argsort_by_u32<T, F>(v: &[T], mut compare: F) -> Vec<u32>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    assert!(
+        v.len() <= u32::MAX as usize,
+        "argsort_by_u32: slice has more than u32::MAX elements"
+    );
+    let mut indices: Vec<u32> = (0..v.len() as u32).collect();
+    // Every index is < `v.len()`.
+    merge_sort(
+        &mut indices,
+        |&a, &b| unsafe {
+            compare(v.get_unchecked(a as usize), v.get_unchecked(b as usize)) == Less
+        },
+        &mut (),
+    );
+    indices
+}
 
-    // Allocate a buffer to use as scratch memory. We keep the length 0 so we can keep in it
-    // shallow copies of the contents of `v` without risking the dtors running on copies if
-    // `is_less` panics. When merging two slices, this buffer holds a copy of the right-hand slice,
-    // which will always have length at most `(len + 1) / 2`.
-    let mut buf = Vec::with_capacity((len + 1) / 2);
-    slice_merge_sort(v, 0, buf.as_mut_ptr(), &mut is_less);
+/// Partially sorts the slice, so that `v[..k]` holds its `k` smallest elements in sorted order.
+///
+/// This sort is stable (i.e., does not reorder equal elements): `v[..k]` is exactly the first
+/// `k` elements [`sort`] would produce. The order of the remaining elements `v[k..]` is
+/// unspecified. If `k >= v.len()` the whole slice is sorted.
+///
+/// # Current implementation
+///
+/// This is the same two stage merge sort as [`sort`], except that each half of a slice longer
+/// than `k` is itself only partially sorted, and only the first `k` elements of each half are
+/// merged. It does *O*(*n* \* log(*k*)) comparisons and moves for `k` much smaller than `n`, and
+/// allocates temporary storage half the size of `self`.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [5, 1, 4, 1, 3, 9, 2];
+///
+/// newsort::partial_sort(&mut v, 3);
+/// assert!(v[..3] == [1, 1, 2]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn partial_sort<T>(v: &mut [T], k: usize)
+where
+    T: Ord,
+{
+    partial_merge_sort(v, k, |a, b| a.lt(b));
+}
 
-    // Do a recursive depth-first merge while slice's length is greater than SMALL_SLICE_LEN*2.
-    // Below that length use a combination of insertion sort and merging.
-    // For optimization, `sorted` tracks how much of the slice's prefix is already sorted.
-    fn slice_merge_sort<T, F>(v: &mut [T], mut sorted: usize, buf_ptr: *mut T, is_less: &mut F)
-    where
-        F: FnMut(&T, &T) -> bool,
-    {
-        let len = v.len();
-        // find length of sorted prefix
-        if sorted == 0 {
-            sorted = if len <= 1 {
-                len
-            } else if gt!(v, 0, 1, is_less) {
-                // strictly descending
-                let mut i = 2;
-                while i < len && gt!(v, i - 1, i, is_less) {
-                    i += 1;
-                }
-                // Reverse the slice so we don't have to sort it later.
-                v[..i].reverse();
-                i
-            } else {
-                // ascending
-                let mut i = 2;
-                while i < len && !gt!(v, i - 1, i, is_less) {
-                    i += 1;
-                }
-                i
+/// Partially sorts the slice with a comparator function, so that `v[..k]` holds its `k` smallest
+/// elements in sorted order.
+///
+/// This sort is stable, see [`partial_sort`]. The comparator function must define a total
+/// ordering for the elements in the slice, see [`sort_by`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [(2, 'a'), (1, 'b'), (2, 'c'), (3, 'd')];
+///
+/// newsort::partial_sort_by(&mut v, 2, |a, b| b.0.cmp(&a.0));
+/// assert!(v[..2] == [(3, 'd'), (2, 'a')]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn partial_sort_by<T, F>(v: &mut [T], k: usize, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    partial_merge_sort(v, k, |a, b| compare(a, b) == Less);
+}
+
+/// Returns clones of the `k` smallest elements of the slice in stable sorted order, leaving the
+/// slice untouched.
+///
+/// The result is the first `k` elements (or all of them, if `k >= v.len()`) that [`sort`] would
+/// produce. Only the selected elements are cloned.
+///
+/// # Examples
+///
+/// ```
+/// let v = ["pear", "fig", "apple", "kiwi"];
+///
+/// assert_eq!(newsort::select_top_k_stable(&v, 2), ["apple", "fig"]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn select_top_k_stable<T>(v: &[T], k: usize) -> Vec<T>
+where
+    T: Ord + Clone,
+{
+    select_top_k_stable_by(v, k, |a, b| a.cmp(b))
+}
+
+/// Returns clones of the `k` smallest elements of the slice in stable sorted order according to
+/// a comparator function, leaving the slice untouched.
+///
+/// See [`select_top_k_stable`] and [`sort_by`].
+///
+/// # Examples
+///
+/// ```
+/// let v = [3, 8, 1, 8, 5];
+///
+/// assert_eq!(newsort::select_top_k_stable_by(&v, 3, |a, b| b.cmp(a)), [8, 8, 5]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub fn select_top_k_stable_by<T, F>(v: &[T], k: usize, mut compare: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut indices: Vec<usize> = (0..v.len()).collect();
+    // Every index is < `v.len()`.
+    partial_merge_sort(&mut indices, k, |&a, &b| unsafe {
+        compare(v.get_unchecked(a), v.get_unchecked(b)) == Less
+    });
+    indices.truncate(k);
+    indices.into_iter().map(|i| v[i].clone()).collect()
+}
+
+/// Merges the sorted slices `left` and `right`, appending clones of their elements to `out`.
+///
+/// The merge is stable: when elements compare equal, the one from `left` comes first. It is
+/// *O*(`left.len()` + `right.len()`). If either input isn't sorted the order of the output is
+/// unspecified.
+///
+/// # Examples
+///
+/// ```
+/// let mut out = vec![0];
+///
+/// newsort::merge_sorted(&[1, 3, 5], &[2, 3, 4], &mut out);
+/// assert_eq!(out, [0, 1, 2, 3, 3, 4, 5]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn merge_sorted<T>(left: &[T], right: &[T], out: &mut Vec<T>)
+where
+    T: Ord + Clone,
+{
+    merge_sorted_by(left, right, out, |a, b| a.cmp(b));
+}
+
+/// Merges the slices `left` and `right`, sorted according to a comparator function, appending
+/// clones of their elements to `out`.
+///
+/// The merge is stable, see [`merge_sorted`].
+///
+/// # Examples
+///
+/// ```
+/// let mut out = Vec::new();
+///
+/// let (a, b) = ([(3, 'a'), (1, 'a')], [(3, 'b'), (2, 'b')]);
+///
+/// newsort::merge_sorted_by(&a, &b, &mut out, |a, b| b.0.cmp(&a.0));
+/// assert_eq!(out, [(3, 'a'), (3, 'b'), (2, 'b'), (1, 'a')]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub fn merge_sorted_by<T, F>(left: &[T], right: &[T], out: &mut Vec<T>, mut compare: F)
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    out.reserve(left.len() + right.len());
+    let (mut l, mut r) = (0, 0);
+    while l < left.len() && r < right.len() {
+        // Consume the lesser side.
+        // If equal, prefer the left run to maintain stability.
+        if compare(&right[r], &left[l]) == Less {
+            out.push(right[r].clone());
+            r += 1;
+        } else {
+            out.push(left[l].clone());
+            l += 1;
+        }
+    }
+    out.extend_from_slice(&left[l..]);
+    out.extend_from_slice(&right[r..]);
+}
+
+/// Merges the sorted runs `v[..mid]` and `v[mid..]` in place, so that the whole slice becomes
+/// sorted.
+///
+/// The merge is stable: when elements compare equal, the one from `v[..mid]` comes first. It
+/// allocates temporary storage the size of the shorter run, or merges without allocating, as by
+/// [`sort_in_place`], when the `alloc` feature is disabled.
+///
+/// # Panics
+///
+/// Panics if `mid > v.len()`.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [1, 4, 7, 2, 3, 8];
+///
+/// newsort::merge_adjacent(&mut v, 3);
+/// assert!(v == [1, 2, 3, 4, 7, 8]);
+/// ```
+#[inline]
+pub fn merge_adjacent<T>(v: &mut [T], mid: usize)
+where
+    T: Ord,
+{
+    merge_adjacent_by(v, mid, |a, b| a.cmp(b));
+}
+
+/// Merges the runs `v[..mid]` and `v[mid..]`, sorted according to a comparator function, in
+/// place.
+///
+/// The merge is stable, see [`merge_adjacent`].
+///
+/// # Panics
+///
+/// Panics if `mid > v.len()`.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [7, 4, 1, 8, 3, 2];
+///
+/// newsort::merge_adjacent_by(&mut v, 3, |a, b| b.cmp(a));
+/// assert!(v == [8, 7, 4, 3, 2, 1]);
+/// ```
+pub fn merge_adjacent_by<T, F>(v: &mut [T], mid: usize, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let len = v.len();
+    assert!(mid <= len, "merge_adjacent_by: mid > len");
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Less;
+    if mid == 0 || mid == len || size_of::<T>() == 0 || !is_less(&v[mid], &v[mid - 1]) {
+        return;
+    }
+
+    #[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+    {
+        let mut buf = Vec::with_capacity(mid.min(len - mid));
+        unsafe {
+            let select = branchless_select::<T>();
+            merge(
+                v.as_mut_ptr(),
+                len,
+                mid,
+                buf.as_mut_ptr(),
+                select,
+                &mut is_less,
+                &mut (),
+            );
+        }
+    }
+    #[cfg(not(all(feature = "alloc", not(no_global_oom_handling))))]
+    merge_in_place(v, mid, &mut is_less, &mut ());
+}
+
+/// Merges any number of sorted slices into a new `Vec`.
+///
+/// The merge is stable: when elements compare equal, the one from the earlier slice comes first.
+/// It is *O*(*n* \* log(*k*)) for *n* elements in *k* slices.
+///
+/// # Current implementation
+///
+/// The slices are cloned into the result one after another, and then adjacent runs are merged
+/// pairwise, as in a bottom-up merge sort, using temporary storage half the size of the result.
+///
+/// # Examples
+///
+/// ```
+/// let shards: [&[i32]; 3] = [&[1, 5, 9], &[2, 6], &[0, 5, 10]];
+///
+/// assert_eq!(newsort::merge_many(&shards), [0, 1, 2, 5, 5, 6, 9, 10]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn merge_many<T>(slices: &[&[T]]) -> Vec<T>
+where
+    T: Ord + Clone,
+{
+    merge_many_by(slices, |a, b| a.cmp(b))
+}
+
+/// Merges any number of slices, sorted according to a comparator function, into a new `Vec`.
+///
+/// The merge is stable, see [`merge_many`].
+///
+/// # Examples
+///
+/// ```
+/// let shards: [&[(u8, char)]; 2] = [&[(2, 'a'), (1, 'a')], &[(2, 'b'), (0, 'b')]];
+///
+/// assert_eq!(
+///     newsort::merge_many_by(&shards, |a, b| b.0.cmp(&a.0)),
+///     [(2, 'a'), (2, 'b'), (1, 'a'), (0, 'b')]
+/// );
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub fn merge_many_by<T, F>(slices: &[&[T]], mut compare: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut v = Vec::with_capacity(slices.iter().map(|s| s.len()).sum());
+    // `bounds[i]..bounds[i + 1]` is the `i`th sorted run of `v`.
+    let mut bounds = Vec::with_capacity(slices.len() + 1);
+    bounds.push(0);
+    for s in slices.iter().filter(|s| !s.is_empty()) {
+        v.extend_from_slice(s);
+        bounds.push(v.len());
+    }
+    if size_of::<T>() == 0 {
+        return v;
+    }
+
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Less;
+    // Merging two adjacent runs copies the shorter one, which is at most half of `v`.
+    let mut buf = Vec::with_capacity(v.len() / 2);
+    while bounds.len() > 2 {
+        let mut merged = 1;
+        for i in (0..bounds.len() - 1).step_by(2) {
+            if i + 2 < bounds.len() {
+                let (start, mid, end) = (bounds[i], bounds[i + 1], bounds[i + 2]);
+                let run = &mut v[start..end];
+                if is_less(&run[mid - start], &run[mid - start - 1]) {
+                    unsafe {
+                        merge(
+                            run.as_mut_ptr(),
+                            run.len(),
+                            mid - start,
+                            buf.as_mut_ptr(),
+                            branchless_select::<T>(),
+                            &mut is_less,
+                            &mut (),
+                        );
+                    }
+                }
+                bounds[merged] = end;
+            } else {
+                bounds[merged] = bounds[i + 1];
+            }
+            merged += 1;
+        }
+        bounds.truncate(merged);
+    }
+    v
+}
+
+/// Sorts the slice, given that its prefix `v[..prefix_len]` is already sorted.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
+/// The prefix is not re-sorted: only the tail `v[prefix_len..]` is sorted, and then merged with
+/// the prefix. If `prefix_len > v.len()` the whole slice is taken to be sorted. If the prefix
+/// isn't actually sorted, the order of the elements is unspecified.
+///
+/// See [`extend_sorted_tail`] to append to a sorted `Vec`.
+///
+/// # Current implementation
+///
+/// This is the same two stage merge sort with pre-sorted prefix optimization as [`sort`], started
+/// with the known prefix. Without the `alloc` feature, the tail is sorted and merged in place as
+/// by [`sort_in_place`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [1, 3, 5, 7, 4, 2, 6];
+///
+/// newsort::sort_with_sorted_prefix(&mut v, 4);
+/// assert!(v == [1, 2, 3, 4, 5, 6, 7]);
+/// ```
+#[inline]
+pub fn sort_with_sorted_prefix<T>(v: &mut [T], prefix_len: usize)
+where
+    T: Ord,
+{
+    sort_with_sorted_prefix_by(v, prefix_len, |a, b| a.cmp(b));
+}
+
+/// Sorts the slice with a comparator function, given that its prefix `v[..prefix_len]` is already
+/// sorted.
+///
+/// This sort is stable, see [`sort_with_sorted_prefix`]. The comparator function must define a
+/// total ordering for the elements in the slice, see [`sort_by`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [9, 5, 1, 4, 8];
+///
+/// newsort::sort_with_sorted_prefix_by(&mut v, 3, |a, b| b.cmp(a));
+/// assert!(v == [9, 8, 5, 4, 1]);
+/// ```
+pub fn sort_with_sorted_prefix_by<T, F>(v: &mut [T], prefix_len: usize, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut is_less = |a: &T, b: &T| compare(a, b) == Less;
+    let len = v.len();
+    let sorted = prefix_len.min(len);
+    // Sorting has no meaningful behavior on zero-sized types.
+    if sorted == len || size_of::<T>() == 0 {
+        return;
+    }
+
+    #[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+    {
+        if len <= MAX_INSERTION {
+            unsafe { Generic::sort_small(v.as_mut_ptr(), len, sorted, &mut is_less, &mut ()) };
+        } else {
+            // See `merge_sort`.
+            let mut buf = Vec::with_capacity(len.div_ceil(2));
+            unsafe {
+                slice_merge_sort::<_, _, _, Generic>(
+                    v.as_mut_ptr(),
+                    len,
+                    (sorted, 0),
+                    buf.as_mut_ptr(),
+                    buf.capacity(),
+                    &mut is_less,
+                    &SortConfig::tuned::<T>(),
+                    &mut (),
+                );
+            }
+        }
+    }
+    #[cfg(not(all(feature = "alloc", not(no_global_oom_handling))))]
+    {
+        merge_sort_in_place(&mut v[sorted..], &mut is_less);
+        merge_in_place(v, sorted, &mut is_less, &mut ());
+    }
+}
+
+/// Appends the elements of `iter` to the sorted `Vec`, keeping it sorted.
+///
+/// Only the appended elements are sorted, and then merged with the existing contents, see
+/// [`sort_with_sorted_prefix`]. This is stable: appended elements are placed after existing
+/// elements which compare equal to them.
+///
+/// # Examples
+///
+/// ```
+/// let mut log = vec![10, 20, 30];
+///
+/// newsort::extend_sorted_tail(&mut log, [25, 5, 40]);
+/// assert_eq!(log, [5, 10, 20, 25, 30, 40]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn extend_sorted_tail<T, I>(vec: &mut Vec<T>, iter: I)
+where
+    T: Ord,
+    I: IntoIterator<Item = T>,
+{
+    extend_sorted_tail_by(vec, iter, |a, b| a.cmp(b));
+}
+
+/// Appends the elements of `iter` to the `Vec`, sorted according to a comparator function,
+/// keeping it sorted.
+///
+/// See [`extend_sorted_tail`] and [`sort_by`].
+///
+/// # Examples
+///
+/// ```
+/// let mut log = vec![(3, "c"), (1, "a")];
+///
+/// newsort::extend_sorted_tail_by(&mut log, [(1, "b"), (2, "b")], |a, b| b.0.cmp(&a.0));
+/// assert_eq!(log, [(3, "c"), (2, "b"), (1, "a"), (1, "b")]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub fn extend_sorted_tail_by<T, I, F>(vec: &mut Vec<T>, iter: I, compare: F)
+where
+    I: IntoIterator<Item = T>,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let prefix_len = vec.len();
+    vec.extend(iter);
+    sort_with_sorted_prefix_by(vec, prefix_len, compare);
+}
+
+/// Sorts the `Vec` and removes consecutive repeated elements, returning the number of elements
+/// removed.
+///
+/// Of each group of equal elements, the first one in the original order is kept, so this is
+/// the same as a stable sort followed by [`Vec::dedup`], but duplicates are dropped while
+/// merging rather than in a second pass. It is *O*(*n* \* log(*n*)) worst-case.
+///
+/// See [`sort_dedup_counts`] to count the duplicates instead.
+///
+/// # Current implementation
+///
+/// This is the two stage merge sort of [`sort`], in which every sorted run is also free of
+/// duplicates: small slices and already sorted runs are compacted, and each merge moves elements
+/// equal to one already taken from the left-hand run out of the way. It allocates temporary
+/// storage half the size of `vec`.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = vec![3, 1, 3, 2, 1];
+///
+/// assert_eq!(newsort::sort_dedup(&mut v), 2);
+/// assert_eq!(v, [1, 2, 3]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_dedup<T>(vec: &mut Vec<T>) -> usize
+where
+    T: Ord,
+{
+    merge_sort_dedup(vec, |a, b| a.lt(b))
+}
+
+/// Sorts the `Vec` with a key extraction function and removes elements with repeated keys,
+/// returning the number of elements removed.
+///
+/// Of each group of elements with equal keys, the first one in the original order is kept. See
+/// [`sort_dedup`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+///
+/// assert_eq!(newsort::sort_dedup_by_key(&mut v, |p| p.0), 2);
+/// assert_eq!(v, [(1, 'b'), (2, 'a')]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_dedup_by_key<T, K, F>(vec: &mut Vec<T>, mut f: F) -> usize
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    merge_sort_dedup(vec, |a, b| f(a).lt(&f(b)))
+}
+
+/// Sorts the `Vec` and removes consecutive repeated elements, returning how many times each of
+/// the remaining elements occurred.
+///
+/// `counts[i]` is the number of elements of the original `vec` equal to `vec[i]`. Of each group
+/// of equal elements, the first one in the original order is kept.
+///
+/// # Current implementation
+///
+/// This is the merge sort of [`sort_dedup`], with the counts moved along with the elements and
+/// each count added to that of the element it repeats when it is dropped as a duplicate. Besides
+/// the counts, it allocates temporary storage half the size of `vec`, and half that of the
+/// counts.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = vec!["b", "a", "b", "c", "b"];
+///
+/// assert_eq!(newsort::sort_dedup_counts(&mut v), [1, 3, 1]);
+/// assert_eq!(v, ["a", "b", "c"]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_dedup_counts<T>(vec: &mut Vec<T>) -> Vec<usize>
+where
+    T: Ord,
+{
+    sort_dedup_counts_impl(vec, |a, b| a.lt(b))
+}
+
+/// Sorts the `Vec` with a key extraction function and removes elements with repeated keys,
+/// returning how many elements had each of the remaining keys.
+///
+/// See [`sort_dedup_counts`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = vec![(2, 'a'), (1, 'b'), (2, 'c')];
+///
+/// assert_eq!(newsort::sort_dedup_counts_by_key(&mut v, |p| p.0), [1, 2]);
+/// assert_eq!(v, [(1, 'b'), (2, 'a')]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_dedup_counts_by_key<T, K, F>(vec: &mut Vec<T>, mut f: F) -> Vec<usize>
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    sort_dedup_counts_impl(vec, |a, b| f(a).lt(&f(b)))
+}
+
+/// Sorts a slice of `Copy` elements.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*))
+/// worst-case. See [`sort_copy_by`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+///
+/// newsort::sort_copy(&mut v);
+/// assert!(v == [(1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_copy<T>(v: &mut [T])
+where
+    T: Copy + Ord,
+{
+    merge_sort_with_config::<_, _, _, Branchless>(
+        v,
+        |a, b| a.lt(b),
+        &SortConfig::tuned::<T>(),
+        &mut (),
+    );
+}
+
+/// Sorts a slice of `Copy` elements with a comparator function.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*))
+/// worst-case.
+///
+/// # Current implementation
+///
+/// This is the same two stage merge sort as [`sort_by`], except that for elements of up to 16
+/// bytes the merge chooses which run to take the next element from without branching. This
+/// avoids the branch mispredictions of merging random data, and is only possible because
+/// elements that are `Copy` are cheap to copy unconditionally.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [5, 4, 1, 3, 2];
+///
+/// newsort::sort_copy_by(&mut v, |a, b| b.cmp(a));
+/// assert!(v == [5, 4, 3, 2, 1]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_copy_by<T, F>(v: &mut [T], mut compare: F)
+where
+    T: Copy,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    merge_sort_with_config::<_, _, _, Branchless>(
+        v,
+        |a, b| compare(a, b) == Less,
+        &SortConfig::tuned::<T>(),
+        &mut (),
+    );
+}
+
+/// Sorts the slice by merging its natural runs.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*))
+/// worst-case. See [`sort_adaptive_by`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [1, 4, 7, 2, 5, 8, 3, 6, 9];
+///
+/// newsort::sort_adaptive(&mut v);
+/// assert!(v == [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_adaptive<T>(v: &mut [T])
+where
+    T: Ord,
+{
+    merge_sort_adaptive(v, |a, b| a.lt(b), &SortConfig::tuned::<T>());
+}
+
+/// Sorts the slice by merging its natural runs, with a comparator function.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*))
+/// worst-case.
+///
+/// # Current implementation
+///
+/// Rather than splitting the slice in halves as [`sort_by`] does, this scans it from the front
+/// for natural runs, non-descending or descending (which are reversed, group by group of equal
+/// elements to keep them in order), and merges neighbouring runs in the order of powersort, which
+/// is within a few percent of the cheapest order for the run lengths. Runs shorter than the
+/// insertion sort length are first extended to it by insertion sort. This finds runs anywhere in
+/// the slice, not just where the halves of [`sort_by`] happen to be sorted, so it is faster than
+/// [`sort_by`] for nearly sorted slices with scattered out of place elements. Long runs are mostly
+/// found by [`sort_by`] too, which is then faster as its merges are balanced. [`sort_by`] is also
+/// faster for random data.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [5, 6, 7, 1, 2, 3, 4, 9, 8];
+///
+/// newsort::sort_adaptive_by(&mut v, |a, b| b.cmp(a));
+/// assert!(v == [9, 8, 7, 6, 5, 4, 3, 2, 1]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_adaptive_by<T, F>(v: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    merge_sort_adaptive(v, |a, b| compare(a, b) == Less, &SortConfig::tuned::<T>());
+}
+
+/// Sorts the slice without allocating.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log²(*n*))
+/// worst-case.
+///
+/// # Current implementation
+///
+/// This is the same two stage merge sort with pre-sorted prefix optimization as [`sort`], but
+/// runs are merged in place by binary searching and rotating, instead of through a temporary
+/// buffer. Nothing is allocated, so it is slower than [`sort`] on large randomly sorted slices,
+/// while being just as fast on sorted, reverse sorted and short slices.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [-5, 4, 1, -3, 2];
+///
+/// newsort::newsort::sort_in_place(&mut v);
+/// assert!(v == [-5, -3, 1, 2, 4]);
+/// ```
+#[inline]
+pub fn sort_in_place<T>(v: &mut [T])
+where
+    T: Ord,
+{
+    merge_sort_in_place(v, |a, b| a.lt(b));
+}
+
+/// Sorts the slice with a comparator function, without allocating.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log²(*n*))
+/// worst-case.
+///
+/// The comparator function must define a total ordering for the elements in the slice, see
+/// [`sort_by`].
+///
+/// # Current implementation
+///
+/// See [`sort_in_place`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [5, 4, 1, 3, 2];
+/// newsort::newsort::sort_in_place_by(&mut v, |a, b| a.cmp(b));
+/// assert!(v == [1, 2, 3, 4, 5]);
+///
+/// // reverse sorting
+/// newsort::newsort::sort_in_place_by(&mut v, |a, b| b.cmp(a));
+/// assert!(v == [5, 4, 3, 2, 1]);
+/// ```
+#[inline]
+pub fn sort_in_place_by<T, F>(v: &mut [T], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    merge_sort_in_place(v, |a, b| compare(a, b) == Less);
+}
+
+/// Sorts the slice, using `buf` as temporary storage.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// `buf` must hold at least `(v.len() + 1) / 2` elements for the buffered merge sort to be used.
+/// If it is shorter, the slice is sorted with [`sort_in_place`] instead, so nothing is ever
+/// allocated. The contents of `buf` are never read, and are left unspecified.
+///
+/// See [`SortScratch`] for a buffer which grows on demand across calls.
+///
+/// # Examples
+///
+/// ```
+/// use std::mem::MaybeUninit;
+///
+/// let mut v = [-5, 4, 1, -3, 2];
+/// let mut buf = [MaybeUninit::uninit(); 3];
+///
+/// newsort::newsort::sort_with_buffer(&mut v, &mut buf);
+/// assert!(v == [-5, -3, 1, 2, 4]);
+/// ```
+#[inline]
+pub fn sort_with_buffer<T>(v: &mut [T], buf: &mut [MaybeUninit<T>])
+where
+    T: Ord,
+{
+    merge_sort_with_buffer(v, buf, |a, b| a.lt(b));
+}
+
+/// Sorts the slice with a comparator function, using `buf` as temporary storage.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// The comparator function must define a total ordering for the elements in the slice, see
+/// [`sort_by`]. `buf` is used as described in [`sort_with_buffer`].
+///
+/// # Examples
+///
+/// ```
+/// use std::mem::MaybeUninit;
+///
+/// let mut v = [5, 4, 1, 3, 2];
+/// let mut buf = [MaybeUninit::uninit(); 3];
+///
+/// newsort::newsort::sort_by_with_buffer(&mut v, &mut buf, |a, b| b.cmp(a));
+/// assert!(v == [5, 4, 3, 2, 1]);
+/// ```
+#[inline]
+pub fn sort_by_with_buffer<T, F>(v: &mut [T], buf: &mut [MaybeUninit<T>], mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    merge_sort_with_buffer(v, buf, |a, b| compare(a, b) == Less);
+}
+
+/// Sorts the slice, allocating its temporary storage from `alloc`.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// This is the same sort as [`sort`], except that the buffer of `(v.len() + 1) / 2` elements is
+/// allocated with `alloc` rather than the global allocator. `Allocator` is the
+/// [`allocator-api2`](https://docs.rs/allocator-api2) trait, which is `core::alloc::Allocator`
+/// when the `nightly` feature is enabled.
+///
+/// # Examples
+///
+/// ```
+/// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// use allocator_api2::alloc::Global;
+///
+/// let mut v = [-5, 4, 1, -3, 2];
+///
+/// newsort::newsort::sort_in(&mut v, Global);
+/// assert!(v == [-5, -3, 1, 2, 4]);
+/// ```
+#[cfg(feature = "allocator-api2")]
+#[inline]
+pub fn sort_in<T, A>(v: &mut [T], alloc: A)
+where
+    T: Ord,
+    A: Allocator,
+{
+    merge_sort_in(v, |a, b| a.lt(b), alloc);
+}
+
+/// Sorts the slice with a comparator function, allocating its temporary storage from `alloc`.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// The comparator function must define a total ordering for the elements in the slice, see
+/// [`sort_by`]. `alloc` is used as described in [`sort_in`].
+///
+/// # Examples
+///
+/// ```
+/// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+/// use allocator_api2::alloc::Global;
+///
+/// let mut v = [5, 4, 1, 3, 2];
+///
+/// newsort::newsort::sort_by_in(&mut v, |a, b| b.cmp(a), &Global);
+/// assert!(v == [5, 4, 3, 2, 1]);
+/// ```
+#[cfg(feature = "allocator-api2")]
+#[inline]
+pub fn sort_by_in<T, F, A>(v: &mut [T], mut compare: F, alloc: A)
+where
+    F: FnMut(&T, &T) -> Ordering,
+    A: Allocator,
+{
+    merge_sort_in(v, |a, b| compare(a, b) == Less, alloc);
+}
+
+/// Sorts the slice, returning an error instead of aborting if its temporary storage can't be
+/// allocated.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// This is the same sort as [`sort`], except that the buffer of `(v.len() + 1) / 2` elements is
+/// reserved with [`Vec::try_reserve_exact`].
+///
+/// # Errors
+///
+/// If the buffer can't be allocated, the error is returned and `v` is left untouched. The slice
+/// can then still be sorted without allocating by [`sort_in_place`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [-5, 4, 1, -3, 2];
+///
+/// newsort::newsort::try_sort(&mut v).unwrap();
+/// assert!(v == [-5, -3, 1, 2, 4]);
+/// ```
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn try_sort<T>(v: &mut [T]) -> Result<(), TryReserveError>
+where
+    T: Ord,
+{
+    try_merge_sort(v, |a, b| a.lt(b))
+}
+
+/// Sorts the slice with a comparator function, returning an error instead of aborting if its
+/// temporary storage can't be allocated.
+///
+/// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// The comparator function must define a total ordering for the elements in the slice, see
+/// [`sort_by`].
+///
+/// # Errors
+///
+/// See [`try_sort`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [5, 4, 1, 3, 2];
+///
+/// newsort::newsort::try_sort_by(&mut v, |a, b| b.cmp(a)).unwrap();
+/// assert!(v == [5, 4, 3, 2, 1]);
+/// ```
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn try_sort_by<T, F>(v: &mut [T], mut compare: F) -> Result<(), TryReserveError>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    try_merge_sort(v, |a, b| compare(a, b) == Less)
+}
+
+/// Reusable scratch memory for sorting many slices without allocating on every call.
+///
+/// The buffer grows on demand to `(v.len() + 1) / 2` elements of the largest slice sorted so far,
+/// and is kept until the `SortScratch` is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use newsort::SortScratch;
+///
+/// let mut scratch = SortScratch::new();
+/// for n in [100, 50, 200] {
+///     let mut batch: Vec<u32> = (0..n).rev().collect();
+///     scratch.sort(&mut batch);
+///     assert!(batch.windows(2).all(|w| w[0] <= w[1]));
+/// }
+/// assert!(scratch.capacity() >= 100);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub struct SortScratch<T> {
+    buf: Vec<T>,
+}
+
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+impl<T> SortScratch<T> {
+    /// Creates an empty `SortScratch`, which does not allocate until it is first used.
+    pub const fn new() -> Self {
+        SortScratch { buf: Vec::new() }
+    }
+
+    /// Creates a `SortScratch` able to sort slices of up to `len` elements without growing.
+    pub fn with_capacity(len: usize) -> Self {
+        SortScratch {
+            buf: Vec::with_capacity(len.div_ceil(2)),
+        }
+    }
+
+    /// Returns the number of elements the scratch buffer holds without growing.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Sorts the slice, growing the scratch buffer if needed. See [`sort`].
+    #[inline]
+    pub fn sort(&mut self, v: &mut [T])
+    where
+        T: Ord,
+    {
+        self.sort_by(v, |a, b| a.cmp(b));
+    }
+
+    /// Sorts the slice with a comparator function, growing the scratch buffer if needed.
+    /// See [`sort_by`].
+    #[inline]
+    pub fn sort_by<F>(&mut self, v: &mut [T], compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        if v.len() > MAX_INSERTION {
+            // `buf` is always empty, so this reserves at least `(v.len() + 1) / 2` elements.
+            self.buf.reserve(v.len().div_ceil(2));
+        }
+        sort_by_with_buffer(v, self.buf.spare_capacity_mut(), compare);
+    }
+
+    /// Sorts the slice with a key extraction function, growing the scratch buffer if needed.
+    /// See [`sort_by_key`].
+    #[inline]
+    pub fn sort_by_key<K, F>(&mut self, v: &mut [T], mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(v, |a, b| f(a).cmp(&f(b)));
+    }
+}
+
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+impl<T> Default for SortScratch<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sorts the slice, returning statistics about the work done.
+///
+/// See [`sort_by_with_stats`].
+///
+/// # Examples
+///
+/// ```
+/// let mut v: Vec<i32> = (0..100).rev().collect();
+///
+/// let stats = newsort::sort_with_stats(&mut v);
+/// assert!(v.windows(2).all(|w| w[0] <= w[1]));
+/// assert_eq!(stats.reversed_runs, 1);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_with_stats<T>(v: &mut [T]) -> SortStats
+where
+    T: Ord,
+{
+    sort_by_with_stats(v, |a, b| a.cmp(b))
+}
+
+/// Sorts the slice with a comparator function, returning statistics about the work done.
+///
+/// The sort is the same as [`sort_by`], instrumented to count comparisons, element moves,
+/// merges and so on, so that production workloads can be profiled without a patched build.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [5, 4, 1, 3, 2];
+///
+/// let stats = newsort::sort_by_with_stats(&mut v, |a, b| a.cmp(b));
+/// assert!(v == [1, 2, 3, 4, 5]);
+/// assert!(stats.comparisons >= 4);
+/// assert_eq!(stats.buffer_bytes, 0);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub fn sort_by_with_stats<T, F>(v: &mut [T], mut compare: F) -> SortStats
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut stats = SortStats::default();
+    let mut comparisons = 0;
+    merge_sort(
+        v,
+        |a, b| {
+            comparisons += 1;
+            compare(a, b) == Less
+        },
+        &mut stats,
+    );
+    stats.comparisons = comparisons;
+    stats
+}
+
+/// Statistics about the work done by a sort, returned by [`sort_by_with_stats`].
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortStats {
+    /// Number of calls to the comparator.
+    pub comparisons: usize,
+    /// Number of elements copied, within the slice or to and from the buffer.
+    pub moves: usize,
+    /// Number of merges of two runs.
+    pub merges: usize,
+    /// Number of slices sorted by insertion sort.
+    pub insertion_runs: usize,
+    /// Number of runs which were reversed: descending runs, reversed by groups of equal elements,
+    /// and pairs of sorted runs which were swapped rather than merged because the whole right run
+    /// goes before the left one.
+    pub reversed_runs: usize,
+    /// Size in bytes of the buffer allocated for merging.
+    pub buffer_bytes: usize,
+}
+
+/// Sorts the slice using the thresholds in `config`.
+///
+/// See [`sort_by_with_config`].
+///
+/// # Examples
+///
+/// ```
+/// use newsort::SortConfig;
+///
+/// let mut v = [-5, 4, 1, -3, 2];
+///
+/// newsort::sort_with_config(&mut v, &SortConfig::new().max_insertion(2));
+/// assert!(v == [-5, -3, 1, 2, 4]);
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_with_config<T>(v: &mut [T], config: &SortConfig)
+where
+    T: Ord,
+{
+    merge_sort_with_config::<_, _, _, Generic>(v, |a, b| a.lt(b), config, &mut ());
+}
+
+/// Sorts the slice with a comparator function, using the thresholds in `config`.
+///
+/// The sort is the same as [`sort_by`], with [`SortConfig`] overriding the slice lengths it was
+/// tuned with, so that it can be tuned for other element sizes and machines.
+///
+/// # Examples
+///
+/// ```
+/// use newsort::SortConfig;
+///
+/// let config = SortConfig::new().small_slice_len(16).max_insertion(32);
+/// let mut v: Vec<u64> = (0..1000).map(|i| i * 7919 % 1000).collect();
+///
+/// newsort::sort_by_with_config(&mut v, &config, |a, b| b.cmp(a));
+/// assert!(v.windows(2).all(|w| w[0] >= w[1]));
+/// ```
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline]
+pub fn sort_by_with_config<T, F>(v: &mut [T], config: &SortConfig, mut compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    merge_sort_with_config::<_, _, _, Generic>(v, |a, b| compare(a, b) == Less, config, &mut ());
+}
+
+/// Thresholds for [`sort_with_config`] and [`par_sort_with_config`](crate::par_sort_with_config).
+///
+/// [`SortConfig::new`] has the lengths the sort was benchmarked with, and [`SortConfig::tuned`] the
+/// ones [`sort`] uses for a given element type.
+///
+/// # Examples
+///
+/// ```
+/// use newsort::SortConfig;
+///
+/// // Large elements are cheaper to merge than to shift along by insertion sort.
+/// let config = SortConfig::new().small_slice_len(4).max_insertion(8);
+/// assert_ne!(config, SortConfig::default());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortConfig {
+    pub(crate) small_slice_len: usize,
+    pub(crate) max_insertion: usize,
+    pub(crate) par_chunk_size: usize,
+    pub(crate) detect_reversed: bool,
+}
+
+impl SortConfig {
+    /// Creates a `SortConfig` with the default thresholds.
+    pub const fn new() -> Self {
+        SortConfig {
+            small_slice_len: SMALL_SLICE_LEN,
+            max_insertion: MAX_INSERTION,
+            par_chunk_size: PAR_CHUNK_SIZE,
+            detect_reversed: true,
+        }
+    }
+
+    /// Creates a `SortConfig` with the thresholds tuned for elements of type `T`.
+    ///
+    /// These are the thresholds [`sort`] uses. `newsort-tune` tunes them for `i16`, `i32`,
+    /// `i64`, `i128` and `String` on the current machine, and `T` gets those of the smallest of
+    /// these types at least as large as it, or else of the largest.
+    pub const fn tuned<T>() -> Self {
+        let size = size_of::<T>();
+        let mut i = 0;
+        while i < TUNED.len() - 1 && TUNED[i].0 < size {
+            i += 1;
+        }
+        TUNED[i].1
+    }
+
+    /// Sets the length below which slices are sorted with insertion sort and then merged, rather
+    /// than split further. Recursion stops at slices of twice this length. Defaults to 10.
+    pub const fn small_slice_len(mut self, len: usize) -> Self {
+        self.small_slice_len = len;
+        self
+    }
+
+    /// Sets the length up to which slices are sorted by insertion sort alone, without allocating
+    /// a buffer. Defaults to 20.
+    pub const fn max_insertion(mut self, len: usize) -> Self {
+        self.max_insertion = len;
+        self
+    }
+
+    /// Sets the length from which the parallel sort splits a slice between threads.
+    /// Defaults to 2560.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is less than 2.
+    pub const fn par_chunk_size(mut self, len: usize) -> Self {
+        assert!(len >= 2, "par_chunk_size: len < 2");
+        self.par_chunk_size = len;
+        self
+    }
+
+    /// Sets whether descending runs are detected and reversed rather than sorted, and two sorted
+    /// runs swapped rather than merged when the whole right run goes before the left one. Runs
+    /// with equal elements are reversed by groups of equal elements, so that the sort stays
+    /// stable. Defaults to `true`.
+    pub const fn detect_reversed(mut self, detect: bool) -> Self {
+        self.detect_reversed = detect;
+        self
+    }
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Sorting
+////////////////////////////////////////////////////////////////////////////////
+
+// `gt!` macro centralizes and clarifies the logic.
+// Unchecked array access gives an approximate 20% performance improvement.
+//
+// `$v` is a slice, or an `ElemPtr` to the elements being sorted.
+//
+// # Safety
+//
+// `$left` and `$right` must be < `$v.len()`, or the number of elements at `$v`
+macro_rules! gt {
+    ($v: ident, $left: expr, $right: expr, $is_less: ident) => {
+        // $is_less(&$v[$right], &$v[$left])
+        $is_less(unsafe { $v.key_at($right) }, unsafe { $v.key_at($left) })
+    };
+}
+
+/// A pointer to the elements `slice_merge_sort` sorts, which are compared by their `Key`.
+///
+/// This is `*mut T` for a slice, and a pair of pointers for the keys and values of
+/// [`sort_pairs`](crate::sort_pairs), which are moved in lockstep. Like pointers into one slice,
+/// these are ordered by address.
+///
+/// # Safety
+///
+/// Each method must behave as the `*mut T` method or `ptr` function of the same name does, for
+/// every pointer at once.
+pub(crate) unsafe trait ElemPtr: Copy + Ord {
+    /// The part of an element which is compared.
+    type Key;
+    /// An element moved out of the slice.
+    type Elem;
+
+    /// Returns the key of the element `self` points to.
+    unsafe fn key<'a>(self) -> &'a Self::Key;
+    unsafe fn add(self, count: usize) -> Self;
+    unsafe fn sub(self, count: usize) -> Self;
+    fn wrapping_add(self, count: usize) -> Self;
+    fn wrapping_sub(self, count: usize) -> Self;
+    unsafe fn offset_from(self, origin: Self) -> isize;
+    unsafe fn copy(src: Self, dst: Self, count: usize);
+    unsafe fn copy_nonoverlapping(src: Self, dst: Self, count: usize);
+    /// Reverses the `len` elements at `self`.
+    unsafe fn reverse(self, len: usize);
+    /// Returns a pointer to `slot`, to hold an element moved out of the slice.
+    fn tmp(slot: &mut MaybeUninit<Self::Elem>) -> Self;
+}
+
+unsafe impl<T> ElemPtr for *mut T {
+    type Key = T;
+    type Elem = T;
+
+    #[inline(always)]
+    unsafe fn key<'a>(self) -> &'a T {
+        unsafe { &*self }
+    }
+    #[inline(always)]
+    unsafe fn add(self, count: usize) -> Self {
+        unsafe { <*mut T>::add(self, count) }
+    }
+    #[inline(always)]
+    unsafe fn sub(self, count: usize) -> Self {
+        unsafe { <*mut T>::sub(self, count) }
+    }
+    #[inline(always)]
+    fn wrapping_add(self, count: usize) -> Self {
+        <*mut T>::wrapping_add(self, count)
+    }
+    #[inline(always)]
+    fn wrapping_sub(self, count: usize) -> Self {
+        <*mut T>::wrapping_sub(self, count)
+    }
+    #[inline(always)]
+    unsafe fn offset_from(self, origin: Self) -> isize {
+        unsafe { <*mut T>::offset_from(self, origin) }
+    }
+    #[inline(always)]
+    unsafe fn copy(src: Self, dst: Self, count: usize) {
+        unsafe { ptr::copy(src, dst, count) }
+    }
+    #[inline(always)]
+    unsafe fn copy_nonoverlapping(src: Self, dst: Self, count: usize) {
+        unsafe { ptr::copy_nonoverlapping(src, dst, count) }
+    }
+    #[inline(always)]
+    unsafe fn reverse(self, len: usize) {
+        unsafe { slice::from_raw_parts_mut(self, len).reverse() }
+    }
+    #[inline(always)]
+    fn tmp(slot: &mut MaybeUninit<T>) -> Self {
+        slot.as_mut_ptr()
+    }
+}
+
+/// Indexes the keys of a slice or of an `ElemPtr`, for `gt!`.
+trait KeyAt {
+    type Key;
+
+    /// Returns the key of element `i`, without bounds checking.
+    unsafe fn key_at(&self, i: usize) -> &Self::Key;
+}
+
+impl<T> KeyAt for [T] {
+    type Key = T;
+
+    #[inline(always)]
+    unsafe fn key_at(&self, i: usize) -> &T {
+        unsafe { self.get_unchecked(i) }
+    }
+}
+
+impl<P: ElemPtr> KeyAt for P {
+    type Key = P::Key;
+
+    #[inline(always)]
+    unsafe fn key_at(&self, i: usize) -> &P::Key {
+        unsafe { self.add(i).key() }
+    }
+}
+
+// Benchmarking determined these are the best sizes.
+// Recursive merge switches to insertion sort / merge when slice length is <= SMALL_SLICE_LEN*2.
+pub(crate) const SMALL_SLICE_LEN: usize = 10;
+// Slices of up to this length get sorted using insertion sort.
+pub(crate) const MAX_INSERTION: usize = 20;
+// The parallel sort splits slices at least this long between threads.
+pub(crate) const PAR_CHUNK_SIZE: usize = SMALL_SLICE_LEN * 256;
+
+/// Hooks through which the sort reports what it is doing, see [`SortStats`].
+///
+/// Every hook does nothing by default, so sorting with `()` compiles to the uninstrumented sort.
+pub(crate) trait Recorder {
+    /// `n` elements were copied within `v` or between `v` and the buffer.
+    #[inline(always)]
+    fn moves(&mut self, _n: usize) {}
+    /// Two runs were merged.
+    #[inline(always)]
+    fn merge(&mut self) {}
+    /// A slice was insertion sorted.
+    #[inline(always)]
+    fn insertion_run(&mut self) {}
+    /// A descending run was reversed, or two sorted runs were swapped.
+    #[inline(always)]
+    fn reversed_run(&mut self) {}
+    /// A buffer of `bytes` bytes was allocated.
+    #[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+    #[inline(always)]
+    fn allocated(&mut self, _bytes: usize) {}
+}
+
+impl Recorder for () {}
+
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+impl Recorder for SortStats {
+    #[inline(always)]
+    fn moves(&mut self, n: usize) {
+        self.moves += n;
+    }
+    #[inline(always)]
+    fn merge(&mut self) {
+        self.merges += 1;
+    }
+    #[inline(always)]
+    fn insertion_run(&mut self) {
+        self.insertion_runs += 1;
+    }
+    #[inline(always)]
+    fn reversed_run(&mut self) {
+        self.reversed_runs += 1;
+    }
+    #[inline(always)]
+    fn allocated(&mut self, bytes: usize) {
+        self.buffer_bytes += bytes;
+    }
+}
+
+/// Inserts `v[len - 1]` into pre-sorted sequence `v[..len - 1]` so that whole `v[..len]` becomes
+/// sorted.
+///
+/// This is the integral subroutine of insertion sort.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements.
+// benchmarking indicated that inlining makes a substantial improvement, yet only requires a couple of hundred bytes
+#[inline(always)]
+#[allow(unused_unsafe)]
+unsafe fn insert_end<P, F, S>(v: P, len: usize, is_less: &mut F, stats: &mut S)
+where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    S: Recorder,
+{
+    let end = len.saturating_sub(1);
+    if end > 0 && gt!(v, end - 1, end, is_less) {
+        unsafe {
+            // There are three ways to implement insertion here:
+            //
+            // 1. Swap adjacent elements until the last one gets to its final destination.
+            //    However, this way we copy data around more than is necessary. If elements are big
+            //    structures (costly to copy), this method will be slow.
+            //
+            // 2. Iterate until the right place for the last element is found. Then shift the
+            //    elements preceeding it to make room for it and finally place it into the
+            //    remaining hole. This is a good method.
+            //
+            // 3. Copy the last element into a temporary variable. Iterate until the right place
+            //    for it is found. As we go along, copy every traversed element into the slot
+            //    succeeding it. Finally, copy data from the temporary variable into the remaining
+            //    hole. This method is very good. Benchmarks demonstrated slightly better
+            //    performance than with the 2nd method.
+            //
+            // All methods were benchmarked, and the 3rd showed best results. So we chose that one.
+            let mut slot = MaybeUninit::uninit();
+            let tmp = P::tmp(&mut slot);
+            P::copy_nonoverlapping(v.add(end), tmp, 1);
+
+            // Intermediate state of the insertion process is always tracked by `hole`, which
+            // serves two purposes:
+            // 1. Protects integrity of `v` from panics in `is_less`.
+            // 2. Fills the remaining hole in `v` in the end.
+            //
+            // Panic safety:
+            //
+            // If `is_less` panics at any point during the process, `hole` will get dropped and
+            // fill the hole in `v` with `tmp`, thus ensuring that `v` still holds every object it
+            // initially held exactly once.
+            let mut hole = InsertionHole { src: tmp, dest: v.add(end - 1) };
+            P::copy_nonoverlapping(hole.dest, v.add(end), 1);
+
+            let mut i = end - 1;
+            while i > 0 && is_less(tmp.key(), v.key_at(i - 1)) {
+                hole.dest = v.add(i - 1);
+                P::copy_nonoverlapping(hole.dest, v.add(i), 1);
+                i -= 1;
+            }
+            // `tmp` is moved out and back in, and `end - i` elements are shifted along.
+            stats.moves(end - i + 2);
+            // `hole` gets dropped and thus copies `tmp` into the remaining hole in `v`.
+        }
+    }
+
+    // When dropped, copies from `src` into `dest`.
+    struct InsertionHole<P: ElemPtr> {
+        src: P,
+        dest: P,
+    }
+
+    impl<P: ElemPtr> Drop for InsertionHole<P> {
+        fn drop(&mut self) {
+            unsafe {
+                P::copy_nonoverlapping(self.src, self.dest, 1);
+            }
+        }
+    }
+}
+
+/// The small slice sort and the merge used by `slice_merge_sort`, which can be specialized for
+/// types that allow faster ones.
+pub(crate) trait Kernel<P: ElemPtr> {
+    /// Whether sorted runs drop repeated elements, see `Kernel::dedup`.
+    const DEDUP: bool = false;
+
+    /// Sorts the `len` elements at `v`, whose first `sorted` are already sorted.
+    ///
+    /// # Safety
+    ///
+    /// `v` must point to `len` elements.
+    unsafe fn sort_small<F, S>(v: P, len: usize, sorted: usize, is_less: &mut F, stats: &mut S)
+    where
+        F: FnMut(&P::Key, &P::Key) -> bool,
+        S: Recorder;
+
+    /// Merges non-decreasing runs `v[..mid]` and `v[mid..len]`, and returns the length of the
+    /// merged run, which is `len` unless `DEDUP` is set.
+    ///
+    /// # Safety
+    ///
+    /// As for `merge`.
+    #[inline(always)]
+    unsafe fn merge<F, S>(
+        v: P,
+        len: usize,
+        mid: usize,
+        buf: P,
+        is_less: &mut F,
+        stats: &mut S,
+    ) -> usize
+    where
+        F: FnMut(&P::Key, &P::Key) -> bool,
+        S: Recorder,
+    {
+        let branchless = branchless_select::<P::Elem>();
+        unsafe { merge(v, len, mid, buf, branchless, is_less, stats) };
+        len
+    }
+
+    /// If `DEDUP` is set, moves the first of each group of equal elements of the sorted run
+    /// `v[..len]` to its front, leaving the others after them, and returns the number of groups.
+    ///
+    /// # Safety
+    ///
+    /// `v` must point to `len` elements, at least one.
+    #[inline(always)]
+    unsafe fn dedup<F, S>(_v: P, len: usize, _is_less: &mut F, _stats: &mut S) -> usize
+    where
+        F: FnMut(&P::Key, &P::Key) -> bool,
+        S: Recorder,
+    {
+        len
+    }
+}
+
+/// Sorts small slices by insertion sort, using `insert_end`, and merges with `merge`.
+pub(crate) struct Generic;
+
+impl<P: ElemPtr> Kernel<P> for Generic {
+    #[inline(always)]
+    #[allow(unused_unsafe)]
+    unsafe fn sort_small<F, S>(v: P, len: usize, sorted: usize, is_less: &mut F, stats: &mut S)
+    where
+        F: FnMut(&P::Key, &P::Key) -> bool,
+        S: Recorder,
+    {
+        stats.insertion_run();
+        for i in sorted..len {
+            unsafe { insert_end(v, i + 1, is_less, stats) };
+        }
+    }
+}
+
+/// Like `Generic`, but merges by `merge_in_place`, for sorting without a buffer.
+struct InPlace;
+
+impl<T> Kernel<*mut T> for InPlace {
+    #[inline(always)]
+    unsafe fn sort_small<F, S>(v: *mut T, len: usize, sorted: usize, is_less: &mut F, stats: &mut S)
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { Generic::sort_small(v, len, sorted, is_less, stats) }
+    }
+
+    #[inline(always)]
+    unsafe fn merge<F, S>(
+        v: *mut T,
+        len: usize,
+        mid: usize,
+        _buf: *mut T,
+        is_less: &mut F,
+        stats: &mut S,
+    ) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        stats.merge();
+        let v = unsafe { slice::from_raw_parts_mut(v, len) };
+        merge_in_place(v, mid, is_less, stats);
+        len
+    }
+}
+
+// `Copy` types up to this size are merged with conditional moves.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+const MAX_BRANCHLESS_SIZE: usize = 16;
+
+/// Like `Generic`, but small `Copy` types are merged picking each element with a conditional move.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub(crate) struct Branchless;
+
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+impl<T> Kernel<*mut T> for Branchless
+where
+    T: Copy,
+{
+    #[inline(always)]
+    unsafe fn sort_small<F, S>(v: *mut T, len: usize, sorted: usize, is_less: &mut F, stats: &mut S)
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { Generic::sort_small(v, len, sorted, is_less, stats) }
+    }
+
+    #[inline(always)]
+    unsafe fn merge<F, S>(
+        v: *mut T,
+        len: usize,
+        mid: usize,
+        buf: *mut T,
+        is_less: &mut F,
+        stats: &mut S,
+    ) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        let branchless = size_of::<T>() <= MAX_BRANCHLESS_SIZE || branchless_select::<T>();
+        unsafe { merge(v, len, mid, buf, branchless, is_less, stats) };
+        len
+    }
+}
+
+/// Finds the length of the sorted prefix of `v[..len]`, reversing it first if it is descending
+/// and `reverse` is set.
+///
+/// A descending prefix is made of groups of equal elements in strictly descending order. Each
+/// group is reversed on its own before the whole prefix is, so that the groups end up ascending
+/// with their elements back in their original order, and equal elements are never reordered.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements.
+#[inline(always)]
+#[allow(unused_unsafe)]
+pub(crate) unsafe fn check_prefix_sort<P, F, S>(
+    v: P,
+    len: usize,
+    reverse: bool,
+    is_less: &mut F,
+    stats: &mut S,
+) -> usize
+where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    S: Recorder,
+{
+    if len <= 1 {
+        return len;
+    }
+
+    // Elements equal to `v[0]` can start either kind of prefix.
+    let mut i = 1;
+    let descending = loop {
+        if i == len {
+            return len;
+        } else if gt!(v, i - 1, i, is_less) {
+            break true;
+        } else if gt!(v, i, i - 1, is_less) {
+            break false;
+        }
+        i += 1;
+    };
+
+    if !descending {
+        // ascending
+        i += 1;
+        while i < len && !gt!(v, i - 1, i, is_less) {
+            i += 1;
+        }
+        i
+    } else if !reverse {
+        i
+    } else {
+        // descending, with `v[..i]` the first group
+        let mut group = 0;
+        let mut moves = 0;
+        while i < len {
+            if gt!(v, i - 1, i, is_less) {
+                unsafe { v.add(group).reverse(i - group) };
+                moves += (i - group) / 2 * 2;
+                group = i;
+            } else if gt!(v, i, i - 1, is_less) {
+                break;
+            }
+            i += 1;
+        }
+        unsafe { v.add(group).reverse(i - group) };
+        moves += (i - group) / 2 * 2;
+        // Reverse the slice so we don't have to sort it later.
+        unsafe { v.reverse(i) };
+        stats.reversed_run();
+        stats.moves(moves + i / 2 * 2);
+        i
+    }
+}
+
+/// Finds the length of the sorted suffix of `v[..len]`, reversing it first if it is descending
+/// and `reverse` is set.
+///
+/// This is `check_prefix_sort` from the other end of `v[..len]`.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements.
+#[inline(always)]
+#[allow(unused_unsafe)]
+pub(crate) unsafe fn check_suffix_sort<P, F, S>(
+    v: P,
+    len: usize,
+    reverse: bool,
+    is_less: &mut F,
+    stats: &mut S,
+) -> usize
+where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    S: Recorder,
+{
+    if len <= 1 {
+        return len;
+    }
+
+    // Elements equal to `v[len - 1]` can end either kind of suffix.
+    let mut i = len - 1;
+    let descending = loop {
+        if i == 0 {
+            return len;
+        } else if gt!(v, i - 1, i, is_less) {
+            break true;
+        } else if gt!(v, i, i - 1, is_less) {
+            break false;
+        }
+        i -= 1;
+    };
+
+    if !descending {
+        // ascending
+        i -= 1;
+        while i > 0 && !gt!(v, i - 1, i, is_less) {
+            i -= 1;
+        }
+        len - i
+    } else if !reverse {
+        len - i
+    } else {
+        // descending, with `v[i..]` the last group
+        let mut group = len;
+        let mut moves = 0;
+        while i > 0 {
+            if gt!(v, i - 1, i, is_less) {
+                unsafe { v.add(i).reverse(group - i) };
+                moves += (group - i) / 2 * 2;
+                group = i;
+            } else if gt!(v, i, i - 1, is_less) {
+                break;
+            }
+            i -= 1;
+        }
+        unsafe { v.add(i).reverse(group - i) };
+        moves += (group - i) / 2 * 2;
+        // Reverse the slice so we don't have to sort it later.
+        unsafe { v.add(i).reverse(len - i) };
+        stats.reversed_run();
+        stats.moves(moves + (len - i) / 2 * 2);
+        len - i
+    }
+}
+
+/// Merges non-decreasing runs `v[..mid]` and `v[mid..len]` using `buf` as temporary storage, and
+/// stores the result into `v[..len]`.
+///
+/// If `branchless` is set, each element is picked with a conditional move rather than a branch,
+/// see `merge_forwards`. Callers without a reason to choose pass `branchless_select::<T>()`.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements. The two runs must be non-empty, and buffer `buf` must be
+/// long enough to hold a copy of the shorter one.
+#[allow(unused_unsafe)]
+pub(crate) unsafe fn merge<P, F, S>(
+    v: P,
+    len: usize,
+    mid: usize,
+    buf: P,
+    branchless: bool,
+    is_less: &mut F,
+    stats: &mut S,
+) where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    S: Recorder,
+{
+    stats.merge();
+    let (v_mid, v_end) = unsafe { (v.add(mid), v.add(len)) };
+
+    // The merge process first copies the shorter run into `buf`. Then it traces the newly copied
+    // run and the longer run forwards (or backwards), comparing their next unconsumed elements and
+    // copying the lesser (or greater) one into `v`.
+    //
+    // As soon as the shorter run is fully consumed, the process is done. If the longer run gets
+    // consumed first, then we must copy whatever is left of the shorter run into the remaining
+    // hole in `v`.
+    //
+    // Intermediate state of the process is always tracked by `hole`, which serves two purposes:
+    // 1. Protects integrity of `v` from panics in `is_less`.
+    // 2. Fills the remaining hole in `v` if the longer run gets consumed first.
+    //
+    // Panic safety:
+    //
+    // If `is_less` panics at any point during the process, `hole` will get dropped and fill the
+    // hole in `v` with the unconsumed range in `buf`, thus ensuring that `v` still holds every
+    // object it initially held exactly once.
+    let mut hole;
+
+    if mid <= len - mid {
+        // The left run is shorter.
+        unsafe {
+            P::copy_nonoverlapping(v, buf, mid);
+            hole = MergeHole { start: buf, end: buf.add(mid), dest: v };
+        }
+
+        // Initially, these pointers point to the beginnings of their arrays.
+        let mut right = v_mid;
+        unsafe {
+            merge_forwards(
+                &mut hole.start,
+                hole.end,
+                &mut right,
+                v_end,
+                &mut hole.dest,
+                branchless,
+                is_less,
+            );
+        }
+        // The left run is copied out and back, and the consumed part of the right run moved down.
+        stats.moves(2 * mid + unsafe { right.offset_from(v_mid) } as usize);
+    } else {
+        // The right run is shorter.
+        unsafe {
+            P::copy_nonoverlapping(v_mid, buf, len - mid);
+            hole = MergeHole { start: buf, end: buf.add(len - mid), dest: v_mid };
+        }
+
+        // Initially, these pointers point past the ends of their arrays.
+        let mut out = v_end;
+        unsafe {
+            merge_backwards(
+                v,
+                &mut hole.dest,
+                buf,
+                &mut hole.end,
+                &mut out,
+                branchless,
+                is_less,
+            );
+        }
+        // The right run is copied out and back, and the consumed part of the left run moved up.
+        stats.moves(2 * (len - mid) + unsafe { v_mid.offset_from(hole.dest) } as usize);
+    }
+    // Finally, `hole` gets dropped. If the shorter run was not fully consumed, whatever remains of
+    // it will now be copied into the hole in `v`.
+}
+
+// Consecutive elements one run must win by before `merge_forwards` and `merge_backwards` start
+// galloping, as in TimSort.
+const MIN_GALLOP: usize = 7;
+
+/// Merges `*left..left_end` and `*right..right_end` forwards into `*dest..`, advancing all three
+/// pointers as elements are placed. If equal, elements of the left run go first.
+///
+/// If `branchless` is set, the run to copy each element from is picked with conditional moves
+/// rather than a branch, which mispredicts about half the time on random data.
+///
+/// Elements are compared one at a time until one run wins `min_gallop` times in a row. Then it
+/// gallops as TimSort does: each run is searched exponentially for how many of its elements go
+/// before the next of the other, and those are copied as a block, until both blocks are shorter
+/// than `MIN_GALLOP`. `min_gallop` starts at `MIN_GALLOP`, falling while galloping pays off and
+/// rising when it stops.
+///
+/// # Safety
+///
+/// The left run must not overlap `dest`, which may overlap the right run only as far as it's
+/// consumed, like the hole of `merge`. The pointers are updated after each copy, so a panic in
+/// `is_less` leaves them describing what was placed.
+#[allow(unused_unsafe)]
+unsafe fn merge_forwards<P, F>(
+    left: &mut P,
+    left_end: P,
+    right: &mut P,
+    right_end: P,
+    dest: &mut P,
+    branchless: bool,
+    is_less: &mut F,
+) where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+{
+    let mut min_gallop = MIN_GALLOP;
+    while *left < left_end && *right < right_end {
+        let (mut left_wins, mut right_wins) = (0, 0);
+        while *left < left_end && *right < right_end && left_wins.max(right_wins) < min_gallop {
+            unsafe {
+                if branchless {
+                    let is_r = is_less(right.key(), left.key());
+                    P::copy_nonoverlapping(if is_r { *right } else { *left }, *dest, 1);
+                    *right = right.add(is_r as usize);
+                    *left = left.add(!is_r as usize);
+                    right_wins = (right_wins + 1) * is_r as usize;
+                    left_wins = (left_wins + 1) * !is_r as usize;
+                } else if is_less(right.key(), left.key()) {
+                    P::copy_nonoverlapping(*right, *dest, 1);
+                    *right = right.add(1);
+                    right_wins += 1;
+                    left_wins = 0;
+                } else {
+                    P::copy_nonoverlapping(*left, *dest, 1);
+                    *left = left.add(1);
+                    left_wins += 1;
+                    right_wins = 0;
+                }
+                *dest = dest.add(1);
+            }
+        }
+
+        while *left < left_end && *right < right_end {
+            min_gallop = min_gallop.saturating_sub(1).max(1);
+            unsafe {
+                // Copy the elements of the left run which don't go after the next right one.
+                let (l, r) = (*left, *right);
+                let n = gallop(left_end.offset_from(l) as usize, |i| {
+                    !is_less(r.key(), l.key_at(i))
+                });
+                P::copy_nonoverlapping(l, *dest, n);
+                *left = l.add(n);
+                *dest = dest.add(n);
+                if *left == left_end {
+                    break;
+                }
+
+                // Copy the elements of the right run which go before the next left one.
+                let l = *left;
+                let m = gallop(right_end.offset_from(r) as usize, |i| {
+                    is_less(r.key_at(i), l.key())
+                });
+                P::copy(r, *dest, m);
+                *right = r.add(m);
+                *dest = dest.add(m);
+                if n < MIN_GALLOP && m < MIN_GALLOP {
+                    min_gallop += 2;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Merges `left_start..*left` and `right_start..*right` backwards into `..*dest`, the mirror
+/// image of `merge_forwards`. If equal, elements of the right run go last.
+///
+/// # Safety
+///
+/// The right run must not overlap `dest`, which may overlap the left run only as far as it's
+/// consumed.
+#[allow(unused_unsafe)]
+unsafe fn merge_backwards<P, F>(
+    left_start: P,
+    left: &mut P,
+    right_start: P,
+    right: &mut P,
+    dest: &mut P,
+    branchless: bool,
+    is_less: &mut F,
+) where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+{
+    let mut min_gallop = MIN_GALLOP;
+    while left_start < *left && right_start < *right {
+        let (mut left_wins, mut right_wins) = (0, 0);
+        while left_start < *left && right_start < *right && left_wins.max(right_wins) < min_gallop {
+            unsafe {
+                *dest = dest.sub(1);
+                if branchless {
+                    let is_l = is_less(right.sub(1).key(), left.sub(1).key());
+                    *left = left.sub(is_l as usize);
+                    *right = right.sub(!is_l as usize);
+                    P::copy_nonoverlapping(if is_l { *left } else { *right }, *dest, 1);
+                    left_wins = (left_wins + 1) * is_l as usize;
+                    right_wins = (right_wins + 1) * !is_l as usize;
+                } else if is_less(right.sub(1).key(), left.sub(1).key()) {
+                    *left = left.sub(1);
+                    P::copy_nonoverlapping(*left, *dest, 1);
+                    left_wins += 1;
+                    right_wins = 0;
+                } else {
+                    *right = right.sub(1);
+                    P::copy_nonoverlapping(*right, *dest, 1);
+                    right_wins += 1;
+                    left_wins = 0;
+                }
+            }
+        }
+
+        while left_start < *left && right_start < *right {
+            min_gallop = min_gallop.saturating_sub(1).max(1);
+            unsafe {
+                // Copy the elements of the left run which go after the last right one.
+                let (l, r) = (*left, *right);
+                let n = gallop(l.offset_from(left_start) as usize, |i| {
+                    is_less(r.sub(1).key(), l.sub(i + 1).key())
+                });
+                *left = l.sub(n);
+                *dest = dest.sub(n);
+                P::copy(*left, *dest, n);
+                if *left == left_start {
+                    break;
+                }
+
+                // Copy the elements of the right run which don't go before the last left one.
+                let l = *left;
+                let m = gallop(r.offset_from(right_start) as usize, |i| {
+                    !is_less(r.sub(i + 1).key(), l.sub(1).key())
+                });
+                *right = r.sub(m);
+                *dest = dest.sub(m);
+                P::copy_nonoverlapping(*right, *dest, m);
+                if n < MIN_GALLOP && m < MIN_GALLOP {
+                    min_gallop += 2;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Returns how many of the first `len` indices `holds` is true for, given that it is true for
+/// some prefix of them, by an exponential search followed by a binary search.
+#[inline]
+fn gallop<F>(len: usize, mut holds: F) -> usize
+where
+    F: FnMut(usize) -> bool,
+{
+    // `holds` is true below `lo`, and false at `lo + step - 1` if that is in bounds.
+    let (mut lo, mut step) = (0, 1);
+    while lo + step <= len && holds(lo + step - 1) {
+        lo += step;
+        step *= 2;
+    }
+    let mut hi = (lo + step - 1).min(len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if holds(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// Merges non-decreasing runs `v[..mid]` and `v[mid..len]` of about equal length, from the front
+/// and the back at once.
+///
+/// The whole of `v` is copied into `buf`, and then each step places both the least and the
+/// greatest of the remaining elements, which are two independent chains of comparisons and
+/// copies. After `len / 2` steps the two meet in the middle, with at most one element left over.
+///
+/// If a block of `GALLOP_BLOCK` steps at the front all take from the same run, the runs are
+/// likely to be long, and the rest is merged by `merge_forwards`, which gallops through them.
+///
+/// # Safety
+///
+/// `v` and `buf` must be valid for `len` elements, and `mid` must be `len / 2` or `(len + 1) / 2`,
+/// which keeps every read in bounds of `buf` even if `is_less` is not a total order. The elements
+/// must not be zero-sized.
+#[allow(unused_unsafe)]
+unsafe fn merge_bidirectional<P, F, S>(
+    v: P,
+    len: usize,
+    mid: usize,
+    buf: P,
+    is_less: &mut F,
+    stats: &mut S,
+) where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    S: Recorder,
+{
+    stats.merge();
+
+    // `buf` keeps every element of `v` exactly once until the merge is complete, so `hole` just
+    // copies it back over `v` if `is_less` panics. Otherwise, it gets emptied at the end.
+    let mut hole;
+    unsafe {
+        P::copy_nonoverlapping(v, buf, len);
+        hole = MergeHole {
+            start: buf,
+            end: buf.add(len),
+            dest: v,
+        };
+    }
+
+    // The front pointers point to the beginnings of the runs, and the back pointers to their last
+    // elements.
+    let (mut left, mut right, mut out) = (buf, unsafe { buf.add(mid) }, v);
+    let (mut left_rev, mut right_rev, mut out_rev) =
+        unsafe { (buf.add(mid - 1), buf.add(len - 1), v.add(len - 1)) };
+
+    let mut steps = len / 2;
+    while steps > 0 {
+        let block = steps.min(GALLOP_BLOCK);
+        let block_start = left;
+        for _ in 0..block {
+            unsafe {
+                // Place the lesser of the first elements, preferring the left run if equal.
+                let is_r = is_less(right.key(), left.key());
+                P::copy_nonoverlapping(if is_r { right } else { left }, out, 1);
+                right = right.add(is_r as usize);
+                left = left.add(!is_r as usize);
+                out = out.add(1);
+
+                // Place the greater of the last elements, preferring the right run if equal.
+                let is_l = is_less(right_rev.key(), left_rev.key());
+                P::copy_nonoverlapping(if is_l { left_rev } else { right_rev }, out_rev, 1);
+                left_rev = left_rev.wrapping_sub(is_l as usize);
+                right_rev = right_rev.wrapping_sub(!is_l as usize);
+                out_rev = out_rev.sub(1);
+            }
+        }
+        steps -= block;
+        if block == GALLOP_BLOCK && (left == block_start || left == block_start.wrapping_add(block))
+        {
+            break;
+        }
+    }
+
+    // What remains of the runs goes into `v[out..=out_rev]`: at most one element, unless the
+    // loop stopped early to gallop. If the counts don't match, `is_less` isn't a total order and
+    // some element was placed twice. Then `hole` restores `v` from `buf`, leaving the runs
+    // unmerged.
+    let (left_end, right_end) = (left_rev.wrapping_add(1), right_rev.wrapping_add(1));
+    unsafe {
+        if left <= left_end
+            && right <= right_end
+            && left_end.offset_from(left) + right_end.offset_from(right)
+                == out_rev.offset_from(out) + 1
+        {
+            merge_forwards(
+                &mut left,
+                left_end,
+                &mut right,
+                right_end,
+                &mut out,
+                branchless_select::<P::Elem>(),
+                is_less,
+            );
+            // One run is used up, and the rest of the other goes last.
+            let n = left_end.offset_from(left) as usize;
+            P::copy_nonoverlapping(left, out, n);
+            P::copy_nonoverlapping(right, out.add(n), right_end.offset_from(right) as usize);
+            hole.start = hole.end;
+        }
+    }
+    stats.moves(2 * len);
+}
+
+// The number of steps after which `merge_bidirectional` checks whether to gallop.
+const GALLOP_BLOCK: usize = 16;
+
+/// Whether `merge_bidirectional` is faster than `merge` for `T`.
+///
+/// On x86_64, LLVM turns its conditional moves back into branches for elements laid out like
+/// 128-bit integers, which mispredict on random data and make it much slower.
+const fn bidirectional<T>() -> bool {
+    !int128_layout::<T>()
+}
+
+/// Whether `merge` picks each element with a conditional move rather than a branch, unless the
+/// kernel chooses otherwise.
+///
+/// This is only done for the elements `merge_bidirectional` is slow for, whose balanced merges on
+/// random data then go through these loops too, and for which LLVM keeps the conditional moves.
+/// Both ways make the same comparisons, so this is correct for any `T`, but it was only measured
+/// to pay off for 128-bit integers: with an expensive `is_less`, a mispredicted branch matters
+/// less than the longer dependency chain.
+const fn branchless_select<T>() -> bool {
+    int128_layout::<T>()
+}
+
+/// Whether `T` has the size and alignment of a 128-bit integer on x86_64.
+const fn int128_layout<T>() -> bool {
+    cfg!(target_arch = "x86_64") && size_of::<T>() == 16 && align_of::<T>() == 16
+}
+
+// When dropped, copies the range `start..end` into `dest..`.
+struct MergeHole<P: ElemPtr> {
+    start: P,
+    end: P,
+    dest: P,
+}
+
+impl<P: ElemPtr> Drop for MergeHole<P> {
+    fn drop(&mut self) {
+        // The elements are not zero-sized, and these are pointers into a slice's elements.
+        unsafe {
+            let len = self.end.offset_from(self.start) as usize;
+            P::copy_nonoverlapping(self.start, self.dest, len);
+        }
+    }
+}
+
+/// This is a stable two stage merge sort with pre-sorted prefix optimization. The two stages are:
+///
+/// 1) Top-down recursive depth-first merge, which helps data locality
+/// 2) Small slices sort using a fast insertion sort, then merge
+///
+/// The total running time is *O*(*n* \* log(*n*)) worst-case.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline(always)]
+fn merge_sort<T, F, S>(v: &mut [T], is_less: F, stats: &mut S)
+where
+    F: FnMut(&T, &T) -> bool,
+    S: Recorder,
+{
+    merge_sort_with_config::<_, _, _, Generic>(v, is_less, &SortConfig::tuned::<T>(), stats);
+}
+
+/// The same two stage merge sort as `merge_sort`, with the thresholds taken from `cfg` and small
+/// slices sorted by `K`.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+pub(crate) fn merge_sort_with_config<T, F, S, K>(
+    v: &mut [T],
+    mut is_less: F,
+    cfg: &SortConfig,
+    stats: &mut S,
+) where
+    F: FnMut(&T, &T) -> bool,
+    S: Recorder,
+    K: Kernel<*mut T>,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if size_of::<T>() == 0 {
+        return;
+    }
+
+    let len = v.len();
+    // Short arrays get sorted in-place via insertion sort to avoid allocations.
+    if len <= cfg.max_insertion {
+        unsafe { K::sort_small(v.as_mut_ptr(), len, 1, &mut is_less, stats) };
+        return;
+    }
+
+    // Allocate a buffer to use as scratch memory. We keep the length 0 so we can keep in it
+    // shallow copies of the contents of `v` without risking the dtors running on copies if
+    // `is_less` panics. When merging two slices, this buffer holds a copy of the right-hand slice,
+    // which will always have length at most `(len + 1) / 2`.
+    let mut buf = Vec::with_capacity(len.div_ceil(2));
+    stats.allocated(buf.capacity() * size_of::<T>());
+    unsafe {
+        slice_merge_sort::<_, _, _, K>(
+            v.as_mut_ptr(),
+            len,
+            (0, 0),
+            buf.as_mut_ptr(),
+            buf.capacity(),
+            &mut is_less,
+            cfg,
+            stats,
+        );
+    }
+}
+
+/// The natural merge sort of `sort_adaptive`, with the thresholds taken from `cfg`.
+///
+/// Runs are found from the front and pushed on a stack, and each boundary between neighbouring
+/// runs gets a power, its depth in a perfectly balanced merge tree over the slice (see
+/// `node_power`). Before a run is pushed, runs on the stack are merged while the boundary below
+/// the top has a greater power than the boundary to the new run. The powers on the stack then
+/// increase from the bottom, and it is never deeper than the bits of `usize`.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+fn merge_sort_adaptive<T, F>(v: &mut [T], mut is_less: F, cfg: &SortConfig)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if size_of::<T>() == 0 {
+        return;
+    }
+
+    let len = v.len();
+    if len <= cfg.max_insertion {
+        unsafe { Generic::sort_small(v.as_mut_ptr(), len, 1, &mut is_less, &mut ()) };
+        return;
+    }
+
+    // See `merge_sort_with_config`. Two neighbouring runs are never both longer than half of `v`.
+    let mut buf = Vec::with_capacity(len.div_ceil(2));
+    let (buf_ptr, buf_len) = (buf.as_mut_ptr(), buf.capacity());
+    let mut runs: Vec<Run> = Vec::new();
+    let mut start = 0;
+    while start < len {
+        let run = unsafe { v.as_mut_ptr().add(start) };
+        let mut end = start
+            + unsafe {
+                check_prefix_sort(run, len - start, cfg.detect_reversed, &mut is_less, &mut ())
             };
+        if end - start < cfg.max_insertion && end < len {
+            // Short runs are extended by insertion sort.
+            let sorted = end - start;
+            end = (start + cfg.max_insertion).min(len);
+            unsafe { Generic::sort_small(run, end - start, sorted, &mut is_less, &mut ()) };
+        }
+
+        if let Some(top) = runs.last() {
+            let power = node_power(top.start, start, end, len);
+            while runs.len() > 1 && runs[runs.len() - 2].power > power {
+                merge_top(v, &mut runs, buf_ptr, buf_len, &mut is_less);
+            }
+            if let Some(top) = runs.last_mut() {
+                top.power = power;
+            }
+        }
+        runs.push(Run {
+            start,
+            len: end - start,
+            power: 0,
+        });
+        start = end;
+    }
+    while runs.len() > 1 {
+        merge_top(v, &mut runs, buf_ptr, buf_len, &mut is_less);
+    }
+
+    // A run of `v[start..start + len]`, and the power of its boundary with the next run.
+    struct Run {
+        start: usize,
+        len: usize,
+        power: u32,
+    }
+
+    // Merges the top two runs on the stack into one.
+    fn merge_top<T, F>(
+        v: &mut [T],
+        runs: &mut Vec<Run>,
+        buf_ptr: *mut T,
+        buf_len: usize,
+        is_less: &mut F,
+    ) where
+        F: FnMut(&T, &T) -> bool,
+    {
+        if let (Some(right), Some(left)) = (runs.pop(), runs.last_mut()) {
+            let (v, mid) = (&mut v[left.start..right.start + right.len], left.len);
+            left.len += right.len;
+            if gt!(v, mid - 1, mid, is_less) {
+                unsafe {
+                    let (v, len) = (v.as_mut_ptr(), v.len());
+                    merge_runs::<_, _, _, Generic>(v, len, mid, buf_ptr, buf_len, is_less, &mut ());
+                }
+            }
+        }
+    }
+
+    // Returns the power of the boundary between runs `start1..start2` and `start2..end` of a slice
+    // of length `n`: the number of halvings of `0..n` after which the midpoints of the two runs
+    // first fall in different halves. The midpoints are kept doubled, as fractions of `n`.
+    fn node_power(start1: usize, start2: usize, end: usize, n: usize) -> u32 {
+        let (mut a, mut b) = (start1 + start2, start2 + end);
+        let mut power = 0;
+        loop {
+            power += 1;
+            if a >= n {
+                a -= n;
+                b -= n;
+            } else if b >= n {
+                return power;
+            }
+            a *= 2;
+            b *= 2;
+        }
+    }
+}
+
+/// The same two stage merge sort as `merge_sort`, removing duplicates as it goes. Returns the
+/// number of elements removed from `vec`.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+fn merge_sort_dedup<T, F>(vec: &mut Vec<T>, mut is_less: F) -> usize
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = vec.len();
+    // Sorting has no meaningful behavior on zero-sized types, so they are all equal.
+    if size_of::<T>() == 0 || len <= 1 {
+        vec.truncate(1);
+        return len - vec.len();
+    }
+
+    let v = vec.as_mut_ptr();
+    let cfg = SortConfig::tuned::<T>();
+    let unique = if len <= cfg.max_insertion {
+        unsafe {
+            Dedup::sort_small(v, len, 1, &mut is_less, &mut ());
+            Dedup::dedup(v, len, &mut is_less, &mut ())
+        }
+    } else {
+        // `merge_dedup` copies the left-hand run, which is at most half of `vec`.
+        let mut buf = Vec::with_capacity(len.div_ceil(2).max(cfg.small_slice_len));
+        unsafe {
+            slice_merge_sort::<_, _, _, Dedup>(
+                v,
+                len,
+                (0, 0),
+                buf.as_mut_ptr(),
+                buf.capacity(),
+                &mut is_less,
+                &cfg,
+                &mut (),
+            )
+        }
+    };
+    vec.truncate(unique);
+    len - unique
+}
+
+/// Sorts `vec` and removes duplicates, returning how many times each remaining element occurred.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+fn sort_dedup_counts_impl<T, F>(vec: &mut Vec<T>, mut is_less: F) -> Vec<usize>
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Each element is paired with the number of elements it stands for, and `DedupCounts` adds
+    // the count of every duplicate to the element it keeps instead.
+    let len = vec.len();
+    let mut counts = alloc::vec![1; len];
+    if size_of::<T>() == 0 || len <= 1 {
+        vec.truncate(1);
+        counts.truncate(1);
+        counts.fill(len);
+        return counts;
+    }
+
+    let v = (vec.as_mut_ptr(), counts.as_mut_ptr());
+    let cfg = SortConfig::tuned::<(T, usize)>();
+    let unique = if len <= cfg.max_insertion {
+        unsafe {
+            DedupCounts::sort_small(v, len, 1, &mut is_less, &mut ());
+            DedupCounts::dedup(v, len, &mut is_less, &mut ())
+        }
+    } else {
+        // See `merge_sort_dedup`. The buffers hold the elements and counts of the left-hand run.
+        let cap = len.div_ceil(2).max(cfg.small_slice_len);
+        let (mut buf, mut count_buf) = (Vec::<T>::with_capacity(cap), Vec::with_capacity(cap));
+        unsafe {
+            slice_merge_sort::<_, _, _, DedupCounts>(
+                v,
+                len,
+                (0, 0),
+                (buf.as_mut_ptr(), count_buf.as_mut_ptr()),
+                cap,
+                &mut is_less,
+                &cfg,
+                &mut (),
+            )
+        }
+    };
+    vec.truncate(unique);
+    counts.truncate(unique);
+    counts
+}
+
+/// Like `Generic`, but drops repeated elements: each sorted run is compacted by `dedup_run`, and
+/// runs are merged by `merge_dedup`.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+struct Dedup;
+
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+impl<T> Kernel<*mut T> for Dedup {
+    const DEDUP: bool = true;
+
+    #[inline(always)]
+    unsafe fn sort_small<F, S>(v: *mut T, len: usize, sorted: usize, is_less: &mut F, stats: &mut S)
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { Generic::sort_small(v, len, sorted, is_less, stats) }
+    }
+
+    #[inline(always)]
+    unsafe fn merge<F, S>(
+        v: *mut T,
+        len: usize,
+        mid: usize,
+        buf: *mut T,
+        is_less: &mut F,
+        stats: &mut S,
+    ) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { merge_dedup(v, len, mid, buf, is_less, &mut |_, _| {}, stats) }
+    }
+
+    #[inline(always)]
+    unsafe fn dedup<F, S>(v: *mut T, len: usize, is_less: &mut F, stats: &mut S) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { dedup_run(v, len, is_less, &mut |_, _| {}, stats) }
+    }
+}
+
+/// `Dedup` for elements paired with counts, which adds the count of each dropped element to the
+/// one it repeats.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+struct DedupCounts;
+
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+impl<T> Kernel<(*mut T, *mut usize)> for DedupCounts {
+    const DEDUP: bool = true;
+
+    #[inline(always)]
+    unsafe fn sort_small<F, S>(
+        v: (*mut T, *mut usize),
+        len: usize,
+        sorted: usize,
+        is_less: &mut F,
+        stats: &mut S,
+    ) where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { Generic::sort_small(v, len, sorted, is_less, stats) }
+    }
+
+    #[inline(always)]
+    unsafe fn merge<F, S>(
+        v: (*mut T, *mut usize),
+        len: usize,
+        mid: usize,
+        buf: (*mut T, *mut usize),
+        is_less: &mut F,
+        stats: &mut S,
+    ) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { merge_dedup(v, len, mid, buf, is_less, &mut add_count, stats) }
+    }
+
+    #[inline(always)]
+    unsafe fn dedup<F, S>(
+        v: (*mut T, *mut usize),
+        len: usize,
+        is_less: &mut F,
+        stats: &mut S,
+    ) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { dedup_run(v, len, is_less, &mut add_count, stats) }
+    }
+}
+
+// Adds the count of a dropped element to the one it repeats.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[inline(always)]
+fn add_count<T>(kept: (*mut T, *mut usize), removed: (*mut T, *mut usize)) {
+    unsafe { *kept.1 += *removed.1 };
+}
+
+/// Moves the first of each group of equal elements of the sorted run `v[..len]` to its front, and
+/// returns the number of groups. `dup(kept, removed)` is called with each of the others, which
+/// are left after them in unspecified order, and the element it repeats.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements, at least one.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[allow(unused_unsafe)]
+unsafe fn dedup_run<P, F, D, S>(
+    v: P,
+    len: usize,
+    is_less: &mut F,
+    dup: &mut D,
+    stats: &mut S,
+) -> usize
+where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    D: FnMut(P, P),
+    S: Recorder,
+{
+    // Everything in `v[unique..i]` is a repeat, so their order doesn't matter. Nothing here moves
+    // an element while `is_less` may panic.
+    let mut unique = 1;
+    for i in 1..len {
+        if gt!(v, i, unique - 1, is_less) {
+            if unique < i {
+                unsafe {
+                    let mut slot = MaybeUninit::uninit();
+                    let tmp = P::tmp(&mut slot);
+                    P::copy_nonoverlapping(v.add(unique), tmp, 1);
+                    P::copy_nonoverlapping(v.add(i), v.add(unique), 1);
+                    P::copy_nonoverlapping(tmp, v.add(i), 1);
+                }
+                stats.moves(3);
+            }
+            unique += 1;
+        } else {
+            unsafe { dup(v.add(unique - 1), v.add(i)) };
         }
+    }
+    unique
+}
 
-        // Do merge sort, using `sorted` to avoid redundant sorting.
-        if sorted < len {
-            if len <= SMALL_SLICE_LEN + 2 {
-                for i in sorted..len {
-                    insert_end(&mut v[..=i], is_less);
-                }
-            } else {
-                let mid;
-                if len > SMALL_SLICE_LEN * 2 {
-                    mid = sorted.max(len / 2);
-                    if sorted < mid {
-                        slice_merge_sort(&mut v[..mid], sorted, buf_ptr, is_less);
-                    }
-                    slice_merge_sort(&mut v[mid..], 0, buf_ptr, is_less);
-                    if !gt!(v, mid - 1, mid, is_less) {
-                        return;
-                    } else if gt!(v, 0, len - 1, is_less) {
-                        // strictly reverse sorted
-                        unsafe {
-                            swap_slices(v, mid, buf_ptr);
-                        }
-                        return;
-                    }
+/// Merges strictly increasing runs `v[..mid]` and `v[mid..len]` into the strictly increasing run
+/// `v[..n]`, using `buf` as temporary storage, and returns `n`. Elements of the right run which are
+/// equal to one in the left run are passed to `dup(kept, removed)` with it, and moved to
+/// `v[n..len]`.
+///
+/// Like `merge_forwards`, the runs are merged one element at a time until one of them wins
+/// `min_gallop` times in a row, and then by galloping.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements. The two runs must be non-empty, and `buf` must be long enough
+/// to hold a copy of `v[..mid]`.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+#[allow(unused_unsafe)]
+unsafe fn merge_dedup<P, F, D, S>(
+    v: P,
+    len: usize,
+    mid: usize,
+    buf: P,
+    is_less: &mut F,
+    dup: &mut D,
+    stats: &mut S,
+) -> usize
+where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    D: FnMut(P, P),
+    S: Recorder,
+{
+    stats.merge();
+
+    // This is the forward pass of `merge`, with the left run copied into `buf`. A duplicate is
+    // always found together with the left element it equals, so there is room to keep it in the
+    // part of `buf` which has already been consumed.
+    //
+    // Panic safety:
+    //
+    // `v[hole.out..r]` is a hole exactly as long as the unconsumed left run plus the stashed
+    // duplicates. If `is_less` panics, `hole` will get dropped and fill it with them.
+    let mut hole = DedupHole {
+        buf,
+        left: 0,
+        left_end: mid,
+        dups: 0,
+        v,
+        out: 0,
+    };
+    unsafe {
+        P::copy_nonoverlapping(v, buf, mid);
+    }
+    let mut r = mid;
+
+    let mut min_gallop = MIN_GALLOP;
+    while hole.left < mid && r < len {
+        let (mut left_wins, mut right_wins) = (0, 0);
+        while hole.left < mid && r < len && left_wins.max(right_wins) < min_gallop {
+            unsafe {
+                let (left, right) = (buf.add(hole.left), v.add(r));
+                if is_less(right.key(), left.key()) {
+                    P::copy_nonoverlapping(right, v.add(hole.out), 1);
+                    r += 1;
+                    right_wins += 1;
+                    left_wins = 0;
                 } else {
-                    for i in sorted..SMALL_SLICE_LEN {
-                        insert_end(&mut v[..=i], is_less);
-                    }
-                    for i in SMALL_SLICE_LEN + 1..len {
-                        insert_end(&mut v[SMALL_SLICE_LEN..=i], is_less);
+                    let is_dup = !is_less(left.key(), right.key());
+                    if is_dup {
+                        // `left` is a copy in `buf`, and `v` still holds every element once.
+                        dup(left, right);
                     }
-                    if !gt!(v, SMALL_SLICE_LEN - 1, SMALL_SLICE_LEN, is_less) {
-                        return;
+                    P::copy_nonoverlapping(left, v.add(hole.out), 1);
+                    hole.left += 1;
+                    if is_dup {
+                        // Prefer the left element to maintain stability.
+                        P::copy_nonoverlapping(right, buf.add(hole.dups), 1);
+                        hole.dups += 1;
+                        r += 1;
                     }
-                    mid = SMALL_SLICE_LEN;
+                    left_wins += 1;
+                    right_wins = 0;
                 }
-                unsafe {
-                    merge(v, mid, buf_ptr, is_less);
+                hole.out += 1;
+            }
+        }
+
+        while hole.left < mid && r < len {
+            min_gallop = min_gallop.saturating_sub(1).max(1);
+            unsafe {
+                // Copy the elements of the left run which go before the next right one. One equal
+                // to it is left for the loop above, which drops the duplicate.
+                let (l, right) = (buf.add(hole.left), v.add(r));
+                let n = gallop(mid - hole.left, |i| is_less(l.key_at(i), right.key()));
+                P::copy_nonoverlapping(l, v.add(hole.out), n);
+                hole.left += n;
+                hole.out += n;
+                if hole.left == mid {
+                    break;
+                }
+
+                // Copy the elements of the right run which go before the next left one.
+                let left = buf.add(hole.left);
+                let m = gallop(len - r, |i| is_less(right.key_at(i), left.key()));
+                P::copy(right, v.add(hole.out), m);
+                r += m;
+                hole.out += m;
+                if n < MIN_GALLOP && m < MIN_GALLOP {
+                    min_gallop += 2;
+                    break;
                 }
             }
         }
     }
+    if r < len {
+        // The left run is consumed, so the hole holds just the duplicates: close it up.
+        unsafe {
+            P::copy(v.add(r), v.add(hole.out), len - r);
+        }
+        hole.out += len - r;
+    }
+    stats.moves(mid + len);
+    // Finally, `hole` gets dropped, copying the rest of the left run and then the duplicates into
+    // the hole.
+    return hole.out + (mid - hole.left);
 
-    /// swap contents of left-hand and right-hand slices divided at `mid`
-    ///
-    /// # Safety
-    ///
-    /// `buf_ptr` must point to a slice of `buf` which is long enough to hold `v[mid..]`
-    ///   `v[mid..].len() <= buf.len()` because buf is sized as `(v.len() + 1) / 2' and `mid == sorted.max(v.len() / 2)`
+    // When dropped, copies `buf[left..left_end]` into `v[out..]`, followed by `buf[..dups]`.
+    struct DedupHole<P: ElemPtr> {
+        buf: P,
+        left: usize,
+        left_end: usize,
+        dups: usize,
+        v: P,
+        out: usize,
+    }
+
+    impl<P: ElemPtr> Drop for DedupHole<P> {
+        fn drop(&mut self) {
+            let rest = self.left_end - self.left;
+            unsafe {
+                P::copy_nonoverlapping(self.buf.add(self.left), self.v.add(self.out), rest);
+                P::copy_nonoverlapping(self.buf, self.v.add(self.out + rest), self.dups);
+            }
+        }
+    }
+}
+
+/// Without the `alloc` feature there is no buffer, so sort in place.
+#[cfg(not(feature = "alloc"))]
+#[inline(always)]
+fn merge_sort<T, F, S>(v: &mut [T], is_less: F, _stats: &mut S)
+where
+    F: FnMut(&T, &T) -> bool,
+    S: Recorder,
+{
+    merge_sort_in_place(v, is_less);
+}
+
+/// The same two stage merge sort as `merge_sort`, only sorting the first `k` positions.
+#[cfg(all(feature = "alloc", not(no_global_oom_handling)))]
+fn partial_merge_sort<T, F>(v: &mut [T], k: usize, mut is_less: F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+    let cfg = SortConfig::tuned::<T>();
+    if k >= len || len <= cfg.max_insertion {
+        merge_sort(v, is_less, &mut ());
+        return;
+    }
+
+    // Sorting has no meaningful behavior on zero-sized types.
+    if k == 0 || size_of::<T>() == 0 {
+        return;
+    }
+
+    // See `merge_sort`: merges only ever copy half of `v` into the buffer.
+    let mut buf = Vec::with_capacity(len.div_ceil(2));
+    slice_partial_merge_sort(v, k, buf.as_mut_ptr(), buf.capacity(), &mut is_less, &cfg);
+
+    /// Like `slice_merge_sort`, but only sorts `v[..k]`, for `0 < k`.
     ///
-    /// `mid` must be <= `v.len()`
-    ///   `mid <= v.len()` because `swap_slices` is only called when: `sorted < len && mid == sorted.max(len / 2)`
-    #[allow(unused_unsafe)]
-    unsafe fn swap_slices<T>(v: &mut [T], mid: usize, buf_ptr: *mut T) {
-        let rlen = v.len() - mid;
-        let v_ptr = v.as_mut_ptr();
+    /// Each half is sorted only as far as it can contribute to the first `k` positions, and the
+    /// merge is pruned to those sorted prefixes.
+    fn slice_partial_merge_sort<T, F>(
+        v: &mut [T],
+        k: usize,
+        buf_ptr: *mut T,
+        buf_len: usize,
+        is_less: &mut F,
+        cfg: &SortConfig,
+    ) where
+        F: FnMut(&T, &T) -> bool,
+    {
+        let len = v.len();
+        if k >= len || len <= cfg.small_slice_len * 2 {
+            unsafe {
+                slice_merge_sort::<_, _, _, Generic>(
+                    v.as_mut_ptr(),
+                    len,
+                    (0, 0),
+                    buf_ptr,
+                    buf_len,
+                    is_less,
+                    cfg,
+                    &mut (),
+                );
+            }
+            return;
+        }
+
+        let mid = len / 2;
+        let (a, b) = (k.min(mid), k.min(len - mid));
+        slice_partial_merge_sort(&mut v[..mid], a, buf_ptr, buf_len, is_less, cfg);
+        slice_partial_merge_sort(&mut v[mid..], b, buf_ptr, buf_len, is_less, cfg);
+
+        // The `k` smallest elements are among the sorted prefixes `v[..a]` and `v[mid..mid + b]`.
+        // The rest of the left half is never among them, so it may follow `v[mid..mid + b]` in
+        // any order, and moving it out of the way takes at most `3 * b` moves, not a rotation.
+        if a < mid {
+            unsafe { close_gap(v.as_mut_ptr().add(a), mid - a, b, buf_ptr, &mut ()) };
+        }
+        let v = &mut v[..a + b];
+        if !gt!(v, a - 1, a, is_less) {
+            return;
+        } else if gt!(v, 0, a + b - 1, is_less) {
+            // strictly reverse sorted
+            unsafe {
+                swap_slices(v.as_mut_ptr(), a + b, a, buf_ptr, buf_len, &mut ());
+            }
+            return;
+        }
+        unsafe {
+            merge_runs::<_, _, _, Generic>(
+                v.as_mut_ptr(),
+                a + b,
+                a,
+                buf_ptr,
+                buf_len,
+                is_less,
+                &mut (),
+            );
+        }
+    }
+}
+
+/// The same two stage merge sort as `merge_sort`, allocating the buffer from `alloc`.
+#[cfg(feature = "allocator-api2")]
+fn merge_sort_in<T, F, A>(v: &mut [T], is_less: F, alloc: A)
+where
+    F: FnMut(&T, &T) -> bool,
+    A: Allocator,
+{
+    // Short arrays and zero-sized types need no buffer.
+    let len = v.len();
+    if len <= MAX_INSERTION || size_of::<T>() == 0 {
+        merge_sort_in_place(v, is_less);
+        return;
+    }
+
+    let mut buf = AllocVec::<T, A>::with_capacity_in(len.div_ceil(2), alloc);
+    merge_sort_with_buffer(v, buf.spare_capacity_mut(), is_less);
+}
+
+/// The same two stage merge sort as `merge_sort`, returning an error if the buffer can't be
+/// allocated.
+#[cfg(feature = "alloc")]
+fn try_merge_sort<T, F>(v: &mut [T], is_less: F) -> Result<(), TryReserveError>
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Short arrays and zero-sized types need no buffer.
+    let len = v.len();
+    if len <= MAX_INSERTION || size_of::<T>() == 0 {
+        merge_sort_in_place(v, is_less);
+        return Ok(());
+    }
+
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(len.div_ceil(2))?;
+    merge_sort_with_buffer(v, buf.spare_capacity_mut(), is_less);
+    Ok(())
+}
+
+/// The same two stage merge sort as `merge_sort`, using the caller's `buf` as scratch memory.
+///
+/// If `buf` is shorter than `(v.len() + 1) / 2`, falls back to `merge_sort_in_place`.
+fn merge_sort_with_buffer<T, F>(v: &mut [T], buf: &mut [MaybeUninit<T>], mut is_less: F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    let len = v.len();
+    if len <= MAX_INSERTION || buf.len() < len.div_ceil(2) {
+        merge_sort_in_place(v, is_less);
+        return;
+    }
+
+    // Sorting has no meaningful behavior on zero-sized types.
+    if size_of::<T>() == 0 {
+        return;
+    }
+
+    // `buf` only ever holds shallow copies of the contents of `v`, which are never dropped.
+    unsafe {
+        slice_merge_sort::<_, _, _, Generic>(
+            v.as_mut_ptr(),
+            len,
+            (0, 0),
+            buf.as_mut_ptr() as *mut T,
+            buf.len(),
+            &mut is_less,
+            &SortConfig::tuned::<T>(),
+            &mut (),
+        );
+    }
+}
+
+/// Do a recursive depth-first merge while slice's length is greater than `cfg.small_slice_len * 2`.
+/// Below that length use a combination of the small sort `K` and merging.
+/// For optimization, `sorted` tracks how much of the slice's prefix and suffix are already sorted,
+/// with 0 for not yet known.
+///
+/// The slice is the `len` elements at `v`. `buf_ptr` points to `buf_len` elements of scratch
+/// memory, at least `(len + 1) / 2`, or none if `K` merges in place. Halves which fit in it
+/// entirely are merged from both ends by `merge_bidirectional`.
+///
+/// Returns the length of the sorted run, which is `len` unless `K::DEDUP` is set. Then each run is
+/// also deduplicated, with the repeated elements moved after it in unspecified order, and the
+/// buffer must also hold `cfg.small_slice_len` elements. Slices of up to `cfg.small_slice_len * 2`
+/// are then sorted whole, since merging short runs which shrink costs more than it saves.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements, and `buf_ptr` to `buf_len` elements which don't overlap them.
+#[allow(unused_unsafe, clippy::too_many_arguments)]
+pub(crate) unsafe fn slice_merge_sort<P, F, S, K>(
+    v: P,
+    len: usize,
+    sorted: (usize, usize),
+    buf_ptr: P,
+    buf_len: usize,
+    is_less: &mut F,
+    cfg: &SortConfig,
+    stats: &mut S,
+) -> usize
+where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    S: Recorder,
+    K: Kernel<P>,
+{
+    let small = cfg.small_slice_len;
+    let (mut prefix, mut suffix) = sorted;
+    if prefix == 0 {
+        // The prefix stops short of a known suffix, which must not be reversed into.
+        prefix = unsafe { check_prefix_sort(v, len - suffix, cfg.detect_reversed, is_less, stats) };
+    }
+
+    // Do merge sort, using `prefix` and `suffix` to avoid redundant sorting.
+    if prefix == len {
+        return unsafe { K::dedup(v, len, is_less, stats) };
+    } else if len <= small + 2 || K::DEDUP && len <= small * 2 {
         unsafe {
-            ptr::copy_nonoverlapping(v_ptr.add(mid), buf_ptr, rlen);
-            ptr::copy(v_ptr, v_ptr.add(rlen), mid);
-            ptr::copy_nonoverlapping(buf_ptr, v_ptr, rlen);
+            K::sort_small(v, len, prefix, is_less, stats);
+            return K::dedup(v, len, is_less, stats);
+        }
+    }
+
+    let (mid, left, right);
+    let large = len > small * 2;
+    if large {
+        if suffix == 0 {
+            suffix = unsafe {
+                check_suffix_sort(
+                    v.add(prefix),
+                    len - prefix,
+                    cfg.detect_reversed,
+                    is_less,
+                    stats,
+                )
+            };
+        }
+        // Split so that a sorted prefix or suffix longer than half of `v` is a whole half. A
+        // deduplicating merge copies the whole left-hand run, so that is never the longer one.
+        mid = if prefix >= len / 2 && !K::DEDUP {
+            prefix
+        } else {
+            (len / 2).min(len - suffix)
+        };
+        left = if prefix < mid {
+            unsafe {
+                slice_merge_sort::<_, _, _, K>(
+                    v,
+                    mid,
+                    (prefix, 0),
+                    buf_ptr,
+                    buf_len,
+                    is_less,
+                    cfg,
+                    stats,
+                )
+            }
+        } else {
+            unsafe { K::dedup(v, mid, is_less, stats) }
+        };
+        right = if suffix < len - mid {
+            unsafe {
+                slice_merge_sort::<_, _, _, K>(
+                    v.add(mid),
+                    len - mid,
+                    (0, suffix),
+                    buf_ptr,
+                    buf_len,
+                    is_less,
+                    cfg,
+                    stats,
+                )
+            }
+        } else {
+            unsafe { K::dedup(v.add(mid), len - mid, is_less, stats) }
+        };
+    } else {
+        mid = small;
+        unsafe {
+            K::sort_small(v, small, prefix.min(small), is_less, stats);
+            K::sort_small(v.add(small), len - small, 1, is_less, stats);
+            left = K::dedup(v, small, is_less, stats);
+            right = K::dedup(v.add(small), len - small, is_less, stats);
+        }
+    }
+
+    if left < mid {
+        // Bring the runs together, moving the repeats of the left-hand run after them.
+        unsafe { close_gap(v.add(left), mid - left, right, buf_ptr, stats) };
+    }
+    let len = left + right;
+    // When deduplicating, the runs are in order only if the left one ends before the right starts.
+    let in_order = if K::DEDUP {
+        gt!(v, left, left - 1, is_less)
+    } else {
+        !gt!(v, left - 1, left, is_less)
+    };
+    if in_order {
+        return len;
+    } else if large && cfg.detect_reversed && gt!(v, 0, len - 1, is_less) {
+        // strictly reverse sorted
+        unsafe {
+            swap_slices(v, len, left, buf_ptr, buf_len, stats);
+        }
+        return len;
+    }
+    unsafe { merge_runs::<_, _, _, K>(v, len, left, buf_ptr, buf_len, is_less, stats) }
+}
+
+/// Merges `v[..mid]` and `v[mid..len]` by `merge_bidirectional` if they are about equally long
+/// and all `len` elements fit in the buffer, and by `K::merge` otherwise. Returns the length of
+/// the merged run.
+///
+/// # Safety
+///
+/// As for `merge`, with `buf_ptr` pointing to `buf_len` elements.
+#[inline(always)]
+unsafe fn merge_runs<P, F, S, K>(
+    v: P,
+    len: usize,
+    mid: usize,
+    buf_ptr: P,
+    buf_len: usize,
+    is_less: &mut F,
+    stats: &mut S,
+) -> usize
+where
+    P: ElemPtr,
+    F: FnMut(&P::Key, &P::Key) -> bool,
+    S: Recorder,
+    K: Kernel<P>,
+{
+    let balanced = mid == len / 2 || mid == len - len / 2;
+    unsafe {
+        if !K::DEDUP && len <= buf_len && balanced && bidirectional::<P::Elem>() {
+            merge_bidirectional(v, len, mid, buf_ptr, is_less, stats);
+            len
+        } else {
+            K::merge(v, len, mid, buf_ptr, is_less, stats)
+        }
+    }
+}
+
+/// Moves the `run` elements at `v.add(gap)` down to `v`, and the `gap` elements they replace to
+/// after them, in unspecified order. At most `gap.min(run)` of these go through `buf`, so a long
+/// gap, such as the repeats left behind by a deduplicated run, is not moved as a whole.
+///
+/// # Safety
+///
+/// `v` must point to `gap + run` elements, and `buf` to `gap.min(run)` which don't overlap them.
+#[allow(unused_unsafe)]
+unsafe fn close_gap<P, S>(v: P, gap: usize, run: usize, buf: P, stats: &mut S)
+where
+    P: ElemPtr,
+    S: Recorder,
+{
+    let n = gap.min(run);
+    unsafe {
+        P::copy_nonoverlapping(v, buf, n);
+        P::copy(v.add(gap), v, run);
+        P::copy_nonoverlapping(buf, v.add(gap.max(run)), n);
+    }
+    stats.moves(2 * n + run);
+}
+
+// Elements larger than this are swapped by `rotate` rather than through the buffer, as moving
+// each element once beats copying the shorter side twice.
+const MAX_BUFFERED_SWAP_SIZE: usize = 64;
+
+// Elements up to this size are rotated by reversals, and larger ones by following cycles.
+const MAX_REVERSAL_ROTATE_SIZE: usize = 16;
+
+/// swap contents of left-hand and right-hand slices of `v[..len]` divided at `mid`
+///
+/// The shorter slice is copied through the buffer, unless it doesn't fit in `buf_len` elements, as
+/// when `merge_sort_in_place` sorts without one, or the elements are larger than
+/// `MAX_BUFFERED_SWAP_SIZE`. Then `v` is rotated in place.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements
+///
+/// `buf_ptr` must point to a slice of `buf` which is `buf_len` long
+///
+/// `mid` must be <= `len`
+///   `mid <= len` because `swap_slices` is only called when both `v[..mid]` and `v[mid..len]`
+///   are sorted
+#[allow(unused_unsafe)]
+unsafe fn swap_slices<P, S>(v: P, len: usize, mid: usize, buf_ptr: P, buf_len: usize, stats: &mut S)
+where
+    P: ElemPtr,
+    S: Recorder,
+{
+    stats.reversed_run();
+    let rlen = len - mid;
+    if size_of::<P::Elem>() > MAX_BUFFERED_SWAP_SIZE || rlen.min(mid) > buf_len {
+        unsafe { rotate(v, len, mid, stats) };
+        return;
+    }
+    unsafe {
+        if rlen <= mid {
+            P::copy_nonoverlapping(v.add(mid), buf_ptr, rlen);
+            P::copy(v, v.add(rlen), mid);
+            P::copy_nonoverlapping(buf_ptr, v, rlen);
+        } else {
+            P::copy_nonoverlapping(v, buf_ptr, mid);
+            P::copy(v.add(mid), v, rlen);
+            P::copy_nonoverlapping(buf_ptr, v.add(rlen), mid);
+        }
+    }
+    stats.moves(2 * rlen.min(mid) + rlen.max(mid));
+}
+
+/// Rotates `v[..len]` left by `mid` in place, like `v[..len].rotate_left(mid)`.
+///
+/// Elements of up to `MAX_REVERSAL_ROTATE_SIZE` bytes are rotated by reversing `v[..mid]` and
+/// `v[mid..len]` and then all of `v[..len]`, which moves each element twice, but in order. Larger
+/// elements are moved once each, along the cycles of the rotation.
+///
+/// # Safety
+///
+/// `v` must point to `len` elements.
+#[allow(unused_unsafe)]
+unsafe fn rotate<P, S>(v: P, len: usize, mid: usize, stats: &mut S)
+where
+    P: ElemPtr,
+    S: Recorder,
+{
+    if mid == 0 || mid == len {
+        return;
+    }
+
+    if size_of::<P::Elem>() <= MAX_REVERSAL_ROTATE_SIZE {
+        unsafe {
+            v.reverse(mid);
+            v.add(mid).reverse(len - mid);
+            v.reverse(len);
+        }
+        stats.moves(mid / 2 * 2 + (len - mid) / 2 * 2 + len / 2 * 2);
+        return;
+    }
+
+    // There are `gcd(len, mid)` cycles, each starting at one of the first elements.
+    let (mut cycles, mut b) = (len, mid);
+    while b != 0 {
+        (cycles, b) = (b, cycles % b);
+    }
+    for start in 0..cycles {
+        // Each cycle takes the element at `start` out, moves the rest of the cycle up by one place,
+        // and writes it into the last place left empty. Nothing here can panic, so `v` is never
+        // seen with an element missing or duplicated.
+        unsafe {
+            let mut slot = MaybeUninit::uninit();
+            let tmp = P::tmp(&mut slot);
+            P::copy_nonoverlapping(v.add(start), tmp, 1);
+            let mut i = start;
+            loop {
+                let j = if i + mid < len {
+                    i + mid
+                } else {
+                    i + mid - len
+                };
+                if j == start {
+                    break;
+                }
+                P::copy_nonoverlapping(v.add(j), v.add(i), 1);
+                i = j;
+            }
+            P::copy_nonoverlapping(tmp, v.add(i), 1);
+        }
+    }
+    stats.moves(len + cycles);
+}
+
+/// The same two stage merge sort as `merge_sort`, except that runs are merged in place by
+/// `merge_in_place`, so no buffer is allocated.
+///
+/// The total running time is *O*(*n* \* log²(*n*)) worst-case.
+fn merge_sort_in_place<T, F>(v: &mut [T], mut is_less: F)
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    // Sorting has no meaningful behavior on zero-sized types.
+    if size_of::<T>() == 0 {
+        return;
+    }
+
+    let (len, cfg) = (v.len(), SortConfig::tuned::<T>());
+    if len <= cfg.max_insertion {
+        unsafe { InPlace::sort_small(v.as_mut_ptr(), len, 1, &mut is_less, &mut ()) };
+        return;
+    }
+
+    // With an empty buffer, reversed halves are swapped by `rotate`, and `InPlace` never reads it.
+    unsafe {
+        slice_merge_sort::<_, _, _, InPlace>(
+            v.as_mut_ptr(),
+            len,
+            (0, 0),
+            ptr::NonNull::dangling().as_ptr(),
+            0,
+            &mut is_less,
+            &cfg,
+            &mut (),
+        );
+    }
+}
+
+/// Merges non-decreasing runs `v[..mid]` and `v[mid..]` in place, without a buffer.
+///
+/// The middle element of the shorter run is used as a pivot: the longer run is binary searched
+/// for the pivot's final position, the block between them is rotated so the pivot lands there,
+/// and the two halves on either side of the pivot are merged recursively. Ties are resolved in
+/// favour of the left run, so the merge is stable.
+///
+/// Short merges are finished with insertion sort.
+fn merge_in_place<T, F, S>(mut v: &mut [T], mut mid: usize, is_less: &mut F, stats: &mut S)
+where
+    F: FnMut(&T, &T) -> bool,
+    S: Recorder,
+{
+    loop {
+        let len = v.len();
+        if mid == 0 || mid == len {
+            return;
+        }
+        if len <= SMALL_SLICE_LEN * 2 {
+            for i in mid..len {
+                unsafe { insert_end(v.as_mut_ptr(), i + 1, is_less, stats) };
+            }
+            return;
+        }
+
+        let (pivot, left_mid, right_mid);
+        if mid <= len - mid {
+            // The left run is shorter: right run elements strictly less than `v[left_mid]` go
+            // before it.
+            left_mid = mid / 2;
+            right_mid = mid + lower_bound(&v[mid..], &v[left_mid], is_less);
+            v[left_mid..right_mid].rotate_left(mid - left_mid);
+            pivot = left_mid + (right_mid - mid);
+        } else {
+            // The right run is shorter: left run elements less than or equal to `v[right_mid]` stay
+            // before it.
+            right_mid = mid + (len - mid) / 2;
+            left_mid = upper_bound(&v[..mid], &v[right_mid], is_less);
+            v[left_mid..=right_mid].rotate_left(mid - left_mid);
+            pivot = left_mid + (right_mid - mid);
+        }
+
+        // `v[..pivot]` holds the runs `v[..left_mid]` and `v[left_mid..pivot]`, and
+        // `v[pivot + 1..]` holds the remainder of both runs. Recurse into the shorter side and
+        // loop on the longer one, to bound the stack depth.
+        let (lo, hi) = v.split_at_mut(pivot);
+        let hi = &mut hi[1..];
+        let hi_mid = mid - left_mid - (mid <= len - mid) as usize;
+        if lo.len() <= hi.len() {
+            merge_in_place(lo, left_mid, is_less, stats);
+            v = hi;
+            mid = hi_mid;
+        } else {
+            merge_in_place(hi, hi_mid, is_less, stats);
+            v = lo;
+            mid = left_mid;
+        }
+    }
+
+    // Returns the index of the first element of `v` which is not less than `x`.
+    fn lower_bound<T, F>(v: &[T], x: &T, is_less: &mut F) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        let (mut lo, mut hi) = (0, v.len());
+        while lo < hi {
+            let m = lo + (hi - lo) / 2;
+            if is_less(&v[m], x) {
+                lo = m + 1;
+            } else {
+                hi = m;
+            }
+        }
+        lo
+    }
+
+    // Returns the index of the first element of `v` which is greater than `x`.
+    fn upper_bound<T, F>(v: &[T], x: &T, is_less: &mut F) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        let (mut lo, mut hi) = (0, v.len());
+        while lo < hi {
+            let m = lo + (hi - lo) / 2;
+            if is_less(x, &v[m]) {
+                hi = m;
+            } else {
+                lo = m + 1;
+            }
         }
+        lo
     }
 }
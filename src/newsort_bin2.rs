@@ -9,6 +9,8 @@ use core::mem::size_of;
 #[cfg(not(no_global_oom_handling))]
 use core::ptr;
 
+use alloc::vec::Vec;
+
 /// Sorts the slice.
 ///
 /// This sort is stable (i.e., does not reorder equal elements) and *O*(*n* \* log(*n*)) worst-case.
@@ -32,7 +34,7 @@ use core::ptr;
 /// ```
 /// let mut v = [-5, 4, 1, -3, 2];
 ///
-/// v.sort();
+/// newsort::newsort_bin2::sort(&mut v);
 /// assert!(v == [-5, -3, 1, 2, 4]);
 /// ```
 #[cfg(not(no_global_oom_handling))]
@@ -61,7 +63,7 @@ where
 ///
 /// ```
 /// let mut floats = [5f64, 4.0, 1.0, 3.0, 2.0];
-/// floats.sort_by(|a, b| a.partial_cmp(b).unwrap());
+/// newsort::newsort_bin2::sort_by(&mut floats, |a, b| a.partial_cmp(b).unwrap());
 /// assert_eq!(floats, [1.0, 2.0, 3.0, 4.0, 5.0]);
 /// ```
 ///
@@ -83,11 +85,11 @@ where
 ///
 /// ```
 /// let mut v = [5, 4, 1, 3, 2];
-/// v.sort_by(|a, b| a.cmp(b));
+/// newsort::newsort_bin2::sort_by(&mut v, |a, b| a.cmp(b));
 /// assert!(v == [1, 2, 3, 4, 5]);
 ///
 /// // reverse sorting
-/// v.sort_by(|a, b| b.cmp(a));
+/// newsort::newsort_bin2::sort_by(&mut v, |a, b| b.cmp(a));
 /// assert!(v == [5, 4, 3, 2, 1]);
 /// ```
 #[cfg(not(no_global_oom_handling))]
@@ -407,7 +409,7 @@ where
     // shallow copies of the contents of `v` without risking the dtors running on copies if
     // `is_less` panics. When merging two slices, this buffer holds a copy of the right-hand slice,
     // which will always have length at most `(len + 1) / 2`.
-    let mut buf = Vec::with_capacity((len + 1) / 2);
+    let mut buf = Vec::with_capacity(len.div_ceil(2));
     slice_merge_sort(v, 0, buf.as_mut_ptr(), &mut is_less);
 
     // Do a recursive depth-first merge while slice's length is greater than SMALL_SLICE_LEN*2.
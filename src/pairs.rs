@@ -0,0 +1,174 @@
+// newsort for parallel key / value slices
+
+use core::cmp::Ordering::{self, Less};
+use core::mem::{size_of, MaybeUninit};
+use core::ptr;
+use core::slice;
+
+use alloc::vec::Vec;
+
+use crate::newsort::{slice_merge_sort, ElemPtr, Generic, Kernel, SortConfig};
+
+/// Sorts `keys`, moving the elements of `vals` along with their keys.
+///
+/// This is equivalent to sorting a slice of `(key, value)` tuples by key, without having to build
+/// one. The sort is stable (i.e., does not reorder equal keys) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// # Panics
+///
+/// Panics if `keys` and `vals` have different lengths.
+///
+/// # Current implementation
+///
+/// The same two stage merge sort as [`sort`](crate::newsort::sort), comparing `keys` and moving
+/// `keys[i]` and `vals[i]` together. It allocates temporary storage half the size of `keys` and
+/// half the size of `vals`.
+///
+/// # Examples
+///
+/// ```
+/// let mut keys = [3, 1, 2, 1];
+/// let mut vals = ["c", "a", "b", "A"];
+///
+/// newsort::sort_pairs(&mut keys, &mut vals);
+/// assert!(keys == [1, 1, 2, 3]);
+/// assert!(vals == ["a", "A", "b", "c"]);
+/// ```
+#[inline]
+pub fn sort_pairs<K, V>(keys: &mut [K], vals: &mut [V])
+where
+    K: Ord,
+{
+    merge_sort_pairs(keys, vals, |a, b| a.lt(b));
+}
+
+/// Sorts `keys` with a comparator function, moving the elements of `vals` along with their keys.
+///
+/// This sort is stable (i.e., does not reorder equal keys) and *O*(*n* \* log(*n*)) worst-case.
+///
+/// The comparator function must define a total ordering for the keys, see
+/// [`sort_by`](crate::newsort::sort_by).
+///
+/// # Panics
+///
+/// Panics if `keys` and `vals` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// let mut keys = [1, 3, 2, 3];
+/// let mut vals = ['a', 'c', 'b', 'C'];
+///
+/// newsort::sort_pairs_by(&mut keys, &mut vals, |a, b| b.cmp(a));
+/// assert!(keys == [3, 3, 2, 1]);
+/// assert!(vals == ['c', 'C', 'b', 'a']);
+/// ```
+#[inline]
+pub fn sort_pairs_by<K, V, F>(keys: &mut [K], vals: &mut [V], mut compare: F)
+where
+    F: FnMut(&K, &K) -> Ordering,
+{
+    merge_sort_pairs(keys, vals, |a, b| compare(a, b) == Less);
+}
+
+/// `merge_sort` from `newsort`, sorting `keys` and moving `vals` along with them.
+fn merge_sort_pairs<K, V, F>(keys: &mut [K], vals: &mut [V], mut is_less: F)
+where
+    F: FnMut(&K, &K) -> bool,
+{
+    assert_eq!(
+        keys.len(),
+        vals.len(),
+        "keys and vals must have the same length"
+    );
+
+    // Sorting has no meaningful behavior on zero-sized keys.
+    let len = keys.len();
+    if size_of::<K>() == 0 || len <= 1 {
+        return;
+    }
+
+    let v = (keys.as_mut_ptr(), vals.as_mut_ptr());
+    let cfg = SortConfig::tuned::<(K, V)>();
+    if len <= cfg.max_insertion {
+        unsafe { Generic::sort_small(v, len, 1, &mut is_less, &mut ()) };
+        return;
+    }
+
+    // See `merge_sort`. The buffers hold shallow copies of the keys and values of the shorter run
+    // being merged, at the same indices.
+    let (mut key_buf, mut val_buf) = (
+        Vec::<K>::with_capacity(len.div_ceil(2)),
+        Vec::<V>::with_capacity(len.div_ceil(2)),
+    );
+    let buf_len = key_buf.capacity().min(val_buf.capacity());
+    unsafe {
+        slice_merge_sort::<_, _, _, Generic>(
+            v,
+            len,
+            (0, 0),
+            (key_buf.as_mut_ptr(), val_buf.as_mut_ptr()),
+            buf_len,
+            &mut is_less,
+            &cfg,
+            &mut (),
+        );
+    }
+}
+
+// Points to `keys[i]` and `vals[i]` at once.
+unsafe impl<K, V> ElemPtr for (*mut K, *mut V) {
+    type Key = K;
+    type Elem = (K, V);
+
+    #[inline(always)]
+    unsafe fn key<'a>(self) -> &'a K {
+        unsafe { &*self.0 }
+    }
+    #[inline(always)]
+    unsafe fn add(self, count: usize) -> Self {
+        unsafe { (self.0.add(count), self.1.add(count)) }
+    }
+    #[inline(always)]
+    unsafe fn sub(self, count: usize) -> Self {
+        unsafe { (self.0.sub(count), self.1.sub(count)) }
+    }
+    #[inline(always)]
+    fn wrapping_add(self, count: usize) -> Self {
+        (self.0.wrapping_add(count), self.1.wrapping_add(count))
+    }
+    #[inline(always)]
+    fn wrapping_sub(self, count: usize) -> Self {
+        (self.0.wrapping_sub(count), self.1.wrapping_sub(count))
+    }
+    #[inline(always)]
+    unsafe fn offset_from(self, origin: Self) -> isize {
+        unsafe { self.0.offset_from(origin.0) }
+    }
+    #[inline(always)]
+    unsafe fn copy(src: Self, dst: Self, count: usize) {
+        unsafe {
+            ptr::copy(src.0, dst.0, count);
+            ptr::copy(src.1, dst.1, count);
+        }
+    }
+    #[inline(always)]
+    unsafe fn copy_nonoverlapping(src: Self, dst: Self, count: usize) {
+        unsafe {
+            ptr::copy_nonoverlapping(src.0, dst.0, count);
+            ptr::copy_nonoverlapping(src.1, dst.1, count);
+        }
+    }
+    #[inline(always)]
+    unsafe fn reverse(self, len: usize) {
+        unsafe {
+            slice::from_raw_parts_mut(self.0, len).reverse();
+            slice::from_raw_parts_mut(self.1, len).reverse();
+        }
+    }
+    #[inline(always)]
+    fn tmp(slot: &mut MaybeUninit<(K, V)>) -> Self {
+        let p = slot.as_mut_ptr();
+        unsafe { (ptr::addr_of_mut!((*p).0), ptr::addr_of_mut!((*p).1)) }
+    }
+}
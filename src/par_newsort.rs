@@ -1,6 +1,9 @@
 // par_newsort-5a
 
 use std::mem::{size_of};
+use std::vec::Vec;
+
+use crate::newsort::{check_prefix_sort, check_suffix_sort, SortConfig};
 
 macro_rules! lt {
     ($v: ident, $left: expr, $right: expr, $is_less: ident) => {
@@ -13,18 +16,13 @@ macro_rules! gt {
         lt!($v, $right, $left, $is_less)
     };
 }
-macro_rules! le {
-    ($v: ident, $left: expr, $right: expr, $is_less: ident) => {
-        ! gt!($v, $left, $right, $is_less)
-    };
-}
 
 #[inline]
 pub fn par_sort<T>(v: &mut [T])
 where
     T: Ord + Send + Sync,
 {
-    merge_sort(v, |a, b| a.lt(b));
+    merge_sort(v, |a, b| a.lt(b), &SortConfig::tuned::<T>());
 }
 
 use std::cmp::Ordering::{self, Less};
@@ -34,20 +32,79 @@ where
     T: Send + Sync,
     F: Fn(&T, &T) -> Ordering + Sync,
 {
-    merge_sort(v, |a, b| compare(a, b) == Less);
+    merge_sort(v, |a, b| compare(a, b) == Less, &SortConfig::tuned::<T>());
+}
+
+#[inline]
+pub fn par_sort_by_key<T, K, F>(v: &mut [T], f: F)
+where
+    T: Send + Sync,
+    K: Ord,
+    F: Fn(&T) -> K + Sync,
+{
+    merge_sort(v, |a, b| f(a).lt(&f(b)), &SortConfig::tuned::<T>());
 }
 
-const SMALL_CHUNK_SIZE: usize = 10;
-const PAR_CHUNK_SIZE: usize = SMALL_CHUNK_SIZE*256;
+/// Sorts the slice in parallel using the thresholds in `config`, including
+/// [`SortConfig::par_chunk_size`].
+///
+/// # Examples
+///
+/// ```
+/// use newsort::SortConfig;
+///
+/// let mut v: Vec<u32> = (0..100_000).rev().collect();
+///
+/// newsort::par_sort_with_config(&mut v, &SortConfig::new().par_chunk_size(1 << 14));
+/// assert!(v.windows(2).all(|w| w[0] <= w[1]));
+///
+/// // Even the smallest slice lengths split down to runs of one or two elements.
+/// for len in 0..=2 {
+///     let mut v: Vec<u32> = (0..10_000u32)
+///         .map(|i| i.wrapping_mul(2_654_435_761) % 1000)
+///         .collect();
+///     let config = SortConfig::new().small_slice_len(len).par_chunk_size(64);
+///
+///     newsort::par_sort_with_config(&mut v, &config);
+///     assert!(v.windows(2).all(|w| w[0] <= w[1]));
+/// }
+/// ```
+#[inline]
+pub fn par_sort_with_config<T>(v: &mut [T], config: &SortConfig)
+where
+    T: Ord + Send + Sync,
+{
+    merge_sort(v, |a, b| a.lt(b), config);
+}
 
-fn merge_sort<T, F>(v: &mut [T], is_less: F)
+/// Sorts the slice in parallel with a comparator function, using the thresholds in `config`.
+///
+/// # Examples
+///
+/// ```
+/// use newsort::SortConfig;
+///
+/// let mut v = [5, 4, 1, 3, 2];
+///
+/// let config = SortConfig::new().detect_reversed(false);
+///
+/// newsort::par_sort_by_with_config(&mut v, &config, |a, b| b.cmp(a));
+/// assert!(v == [5, 4, 3, 2, 1]);
+/// ```
+#[inline]
+pub fn par_sort_by_with_config<T, F>(v: &mut [T], config: &SortConfig, compare: F)
 where
     T: Send + Sync,
-    F: Fn(&T, &T) -> bool + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
 {
-    // Slices of up to this length get sorted using insertion sort.
-    const MAX_INSERTION: usize = 20;
+    merge_sort(v, |a, b| compare(a, b) == Less, config);
+}
 
+fn merge_sort<T, F>(v: &mut [T], is_less: F, cfg: &SortConfig)
+where
+    T: Send + Sync,
+    F: Fn(&T, &T) -> bool + Sync,
+{
     // Sorting has no meaningful behavior on zero-sized types.
     if size_of::<T>() == 0 {
         return;
@@ -56,7 +113,8 @@ where
     let len = v.len();
     let is_less = &is_less;
 
-    if len <= MAX_INSERTION {
+    // Slices of up to `cfg.max_insertion` get sorted using insertion sort.
+    if len <= cfg.max_insertion {
         // Short arrays get sorted in-place via insertion sort to avoid allocations.
         if len > 1 {
             for i in (0..len - 1).rev() {
@@ -74,26 +132,33 @@ where
     let num_threads = rayon::current_num_threads();
 
     unsafe { buf.set_len(len) }
-    par_chunks_sort(v, num_threads, 0, &mut buf, is_less);
+    par_chunks_sort(v, num_threads, 0, &mut buf, is_less, cfg);
     unsafe { buf.set_len(0) }
 
-    fn par_chunks_sort<T, F>(v: &mut [T], num_threads: usize, depth: usize, buf: &mut [T], is_less: &F) -> bool
+    fn par_chunks_sort<T, F>(
+        v: &mut [T],
+        num_threads: usize,
+        depth: usize,
+        buf: &mut [T],
+        is_less: &F,
+        cfg: &SortConfig,
+    ) -> bool
     where
         T: Send + Sync,
         F: Fn(&T, &T) -> bool + Sync,
     {
         let len = v.len();
 
-        if len < PAR_CHUNK_SIZE || num_threads < 2 {
-            large_chunks_sort(v, 0, buf.as_mut_ptr(), is_less);
+        if len < cfg.par_chunk_size || num_threads < 2 {
+            large_chunks_sort(v, (0, 0), buf.as_mut_ptr(), is_less, cfg);
             false
         } else {
-            let mid = (len + 1)/2;
+            let mid = len.div_ceil(2);
             let (lo, hi) = v.split_at_mut(mid);
             let (buf_lo, buf_hi) = buf.split_at_mut(mid);
             let (v, buf, swapped) = match rayon::join(
-                    || par_chunks_sort(lo, num_threads/2, depth + 1, buf_lo, is_less),
-                    || par_chunks_sort(hi, num_threads/2, depth + 1, buf_hi, is_less)) {
+                    || par_chunks_sort(lo, num_threads/2, depth + 1, buf_lo, is_less, cfg),
+                    || par_chunks_sort(hi, num_threads/2, depth + 1, buf_hi, is_less, cfg)) {
                 (false, false) => {
                     (v, buf, false)
                 }
@@ -110,14 +175,15 @@ where
                 }
             };
             if depth == 0 && swapped {
-                par_merge(v, mid, buf, is_less, num_threads);
+                par_merge(v, mid, buf, is_less, num_threads, cfg);
                 false
             } else if gt!(v, mid - 1, mid, is_less) {
-                if gt!(v, 0, len - 1, is_less) {  // strictly reverse sorted?
+                if cfg.detect_reversed && gt!(v, 0, len - 1, is_less) {
+                    // strictly reverse sorted?
                     swap_buf(v, mid, buf.as_mut_ptr());
                     swapped
-                } else if depth > 0 && len >= PAR_CHUNK_SIZE*2 {
-                    par_merge(v, mid, buf, is_less, num_threads);
+                } else if depth > 0 && len >= cfg.par_chunk_size * 2 {
+                    par_merge(v, mid, buf, is_less, num_threads, cfg);
                     ! swapped
                 } else {
                     merge(v, mid, buf.as_mut_ptr(), is_less);
@@ -129,45 +195,86 @@ where
         }
     }
 
-    fn large_chunks_sort<T, F>(v: &mut [T], mut sorted: usize, buf_ptr: *mut T, is_less: &F)
-    where
+    // `sorted` is the length of the sorted prefix and suffix of `v`, 0 if not yet known
+    fn large_chunks_sort<T, F>(
+        v: &mut [T],
+        sorted: (usize, usize),
+        buf_ptr: *mut T,
+        is_less: &F,
+        cfg: &SortConfig,
+    ) where
         F: Fn(&T, &T) -> bool + Sync,
     {
         let len = v.len();
-        if sorted == 0 {
-            sorted = check_prefix_sort(v, is_less);
+        let small = cfg.small_slice_len;
+        let (mut prefix, mut suffix) = sorted;
+        if prefix == 0 {
+            // stop short of the suffix, so as not to reverse into it
+            prefix = unsafe {
+                check_prefix_sort(
+                    v.as_mut_ptr(),
+                    len - suffix,
+                    cfg.detect_reversed,
+                    &mut { is_less },
+                    &mut (),
+                )
+            };
         }
 
-        if sorted < len {
-            debug_assert!(len > 2);
-            if len <= SMALL_CHUNK_SIZE + 2 {
+        if prefix < len {
+            debug_assert!(len >= 2);
+            if len <= small + 2 {
                 for i in (0..len - 1).rev() {
                     insert_head(&mut v[i..len], is_less);
                 }
             } else {
                 let mid;
 
-                if len > SMALL_CHUNK_SIZE*2 {
-                    mid = (len + 1)/2;
-                    if sorted < mid {
-                        large_chunks_sort(&mut v[..mid], sorted, buf_ptr, is_less);
-                        large_chunks_sort(&mut v[mid..], 0, buf_ptr, is_less);        
-                    } else {
-                        large_chunks_sort(&mut v[mid..], sorted - mid, buf_ptr, is_less);
+                if len > small * 2 {
+                    if suffix == 0 {
+                        suffix = unsafe {
+                            check_suffix_sort(
+                                v.as_mut_ptr().add(prefix),
+                                len - prefix,
+                                cfg.detect_reversed,
+                                &mut { is_less },
+                                &mut (),
+                            )
+                        };
+                    }
+                    mid = len.div_ceil(2);
+                    if prefix < mid {
+                        large_chunks_sort(
+                            &mut v[..mid],
+                            (prefix, suffix.saturating_sub(len - mid)),
+                            buf_ptr,
+                            is_less,
+                            cfg,
+                        );
+                    }
+                    if suffix < len - mid {
+                        large_chunks_sort(
+                            &mut v[mid..],
+                            (prefix.saturating_sub(mid), suffix),
+                            buf_ptr,
+                            is_less,
+                            cfg,
+                        );
                     }
                 } else {
-                    mid = SMALL_CHUNK_SIZE;
-                    if sorted < SMALL_CHUNK_SIZE {
-                        for i in (0..SMALL_CHUNK_SIZE - 1).rev() {
-                            insert_head(&mut v[i..SMALL_CHUNK_SIZE], is_less);
+                    mid = small;
+                    if prefix < small {
+                        for i in (0..small - 1).rev() {
+                            insert_head(&mut v[i..small], is_less);
                         }
                     }
-                    for i in (SMALL_CHUNK_SIZE..len - 1).rev() {
+                    for i in (small..len - 1).rev() {
                         insert_head(&mut v[i..], is_less);
                     }
                 }
                 if gt!(v, mid - 1, mid, is_less) {
-                    if gt!(v, 0, len - 1, is_less) {  // strictly reverse sorted?
+                    if cfg.detect_reversed && gt!(v, 0, len - 1, is_less) {
+                        // strictly reverse sorted?
                         swap_buf(v, mid, buf_ptr);
                     } else {
                         merge(v, mid, buf_ptr, is_less);
@@ -181,8 +288,14 @@ where
     unsafe impl<T> Send for MergePart<'_, T> {}
     
     use rayon::prelude::*;
-    fn par_merge<T, F>(v: &mut [T], mid: usize, buf: &mut [T], is_less: &F, num_threads: usize)
-    where
+    fn par_merge<T, F>(
+        v: &mut [T],
+        mid: usize,
+        buf: &mut [T],
+        is_less: &F,
+        num_threads: usize,
+        cfg: &SortConfig,
+    ) where
         T: Sync,
         F: Fn(&T, &T) -> bool + Sync,
     {
@@ -192,16 +305,16 @@ where
 
         // let num_threads = 1.max(rayon::current_num_threads()/2);
         // num_threads = num_threads.max(2);
-        let psize = PAR_CHUNK_SIZE.max((b.len() + num_threads - 1)/num_threads);
+        let psize = cfg.par_chunk_size.max(b.len().div_ceil(num_threads));
         let p: Vec<usize> = b.par_chunks(psize)
             .map(|bpar| binary_search(a, &bpar[bpar.len() - 1], is_less))
             .collect();
     
-        let mut parts: Vec<MergePart<T>> = vec![];
+        let mut parts: Vec<MergePart<T>> = Vec::new();
         let mut j = 0;
-        for i in 0..p.len() {
-            debug_assert!(p[i] >= j);
-            let split = p[i] - j;
+        for &pi in &p {
+            debug_assert!(pi >= j);
+            let split = pi - j;
             let (al, ar) = a.split_at(split);
             j += split;
             a = ar;
@@ -211,7 +324,7 @@ where
             c = cr;
             parts.push(MergePart(al, bl, cl));
         }
-        if a.len() != 0 || b.len() != 0 {
+        if !a.is_empty() || !b.is_empty() {
             parts.push(MergePart(a, b, c));
         }
 
@@ -281,7 +394,7 @@ where
     where
         F: Fn(&T, &T) -> bool,
     {
-        debug_assert!(v.len() > 0);
+        debug_assert!(!v.is_empty());
         let mid = v.len()/2;
         if mid == 0 {
             if is_less(x, &v[0]) {
@@ -298,31 +411,6 @@ where
         }
     }
 
-    // find length of sorted prefix and reverse it if strictly descending
-    #[inline(always)]
-    fn check_prefix_sort<T, F>(v: &mut [T], is_less: &F) -> usize
-    where
-        F: Fn(&T, &T) -> bool,
-    {
-        let len = v.len();
-        if len <= 1 {
-            len
-        } else if gt!(v, 0, 1, is_less) { // strictly descending
-            let mut i = 2;
-            while i < len && gt!(v, i - 1, i, is_less) {
-                i += 1;
-            }
-            v[..i].reverse();
-            i
-        } else {
-            let mut i = 2;
-            while i < len && le!(v, i - 1, i, is_less) {
-                i += 1;
-            }
-            i
-        }
-    }
-
     use std::ptr;
     fn swap_buf<T>(v: &mut [T], mid: usize, buf_ptr: *mut T) {
         let rlen = v.len() - mid;
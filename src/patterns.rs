@@ -0,0 +1,109 @@
+// benchmark data patterns, shared by the newsort benchmark and newsort-tune
+
+use std::cmp::min;
+use std::fmt::{self, Display, Formatter};
+use std::ops::RemAssign;
+
+#[derive(Copy,Clone)]
+pub enum Pattern {
+    Sawtooth,
+    Rand,
+    Stagger,
+    Plateau,
+    Shuffle,
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Pattern::Sawtooth => "sawtooth",
+            Pattern::Rand => "rand",
+            Pattern::Stagger => "stagger",
+            Pattern::Plateau => "plateau",
+            Pattern::Shuffle => "shuffle",
+        }.fmt(f)
+    }
+}
+
+#[derive(Copy,Clone)]
+pub enum Variant {
+    Ident,
+    Reverse,
+    ReverseFront,
+    ReverseBack,
+    Sorted,
+    ReverseSorted,
+    Dither,
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Variant::Ident => "ident",
+            Variant::Reverse => "reverse",
+            Variant::ReverseFront => "reverse_front",
+            Variant::ReverseBack => "reverse_back",
+            Variant::Sorted => "sorted",
+            Variant::ReverseSorted => "reverse_sorted",
+            Variant::Dither => "dither",
+        }.fmt(f)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum TestType {
+    BenchMark,
+    EqualityTest,
+}
+
+// Integer types the patterns can be generated as, truncating like `as`.
+pub trait FromUsize {
+    fn from_usize(x: usize) -> Self;
+}
+
+macro_rules! from_usize {
+    ($($t: ty),*) => {
+        $(impl FromUsize for $t {
+            fn from_usize(x: usize) -> Self {
+                x as $t
+            }
+        })*
+    };
+}
+from_usize!(i16, i32, i64, i128);
+
+pub fn generate_int<T>(pattern: Pattern, variant: Variant, size: usize, rand_size: bool, m: usize, test_type: TestType, run: usize) -> Vec<T>
+where
+    T: FromUsize + Ord + RemAssign,
+{
+    use rand::prelude::*;
+    let mut rng =
+        if let TestType::BenchMark = test_type {
+            StdRng::seed_from_u64((m*size + run) as u64)
+        } else {
+            StdRng::from_entropy()
+        };
+    let rsize = (rng.gen::<f32>()*10.0*size as f32) as usize;
+    let size = if rand_size { rsize } else { size };
+    let mut ret_val = Vec::with_capacity(size);
+    let (mut j, mut k) = (0, 0);
+    for i in 0 .. size {
+        ret_val.push(T::from_usize(match pattern {
+            Pattern::Sawtooth => i % m,
+            Pattern::Rand => rng.gen::<usize>(),
+            Pattern::Stagger => (i*m + i) % size,
+            Pattern::Plateau => min(i, m),
+            Pattern::Shuffle => if rng.gen::<usize>() % m == 0 { j+=2; j } else { k += 2; k },
+        }));
+    }
+    match variant {
+        Variant::Ident => (),
+        Variant::Reverse => ret_val.reverse(),
+        Variant::ReverseFront => ret_val[0 .. size / 2].reverse(),
+        Variant::ReverseBack => ret_val[size / 2 .. ].reverse(),
+        Variant::Sorted => ret_val.sort(),
+        Variant::ReverseSorted => { ret_val.sort(); ret_val.reverse() },
+        Variant::Dither => for x in &mut ret_val { *x %= T::from_usize(5); },
+    }
+    ret_val
+}
@@ -0,0 +1,199 @@
+// newsort for integer and float primitives
+
+use core::cmp::Ordering::Less;
+use core::slice;
+
+use crate::newsort::{merge, merge_sort_with_config, Kernel, Recorder, SortConfig};
+
+mod private {
+    /// The order `sort_primitive` sorts in. Elements which are equal in it are identical, so the
+    /// small slice networks need not be stable.
+    pub trait Sealed: Copy {
+        fn lt(&self, other: &Self) -> bool;
+    }
+}
+
+/// Integer and float primitives, which [`sort_primitive`] sorts with kernels specialized for
+/// small `Copy` elements.
+///
+/// This trait is sealed and cannot be implemented outside this crate.
+pub trait Primitive: private::Sealed {}
+
+macro_rules! primitive_int {
+    ($($t: ty),*) => {
+        $(impl private::Sealed for $t {
+            #[inline(always)]
+            fn lt(&self, other: &Self) -> bool {
+                *self < *other
+            }
+        }
+        impl Primitive for $t {})*
+    };
+}
+primitive_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! primitive_float {
+    ($($t: ty),*) => {
+        $(impl private::Sealed for $t {
+            #[inline(always)]
+            fn lt(&self, other: &Self) -> bool {
+                self.total_cmp(other) == Less
+            }
+        }
+        impl Primitive for $t {})*
+    };
+}
+primitive_float!(f32, f64);
+
+/// Sorts a slice of integer or float primitives.
+///
+/// Integers are sorted in ascending order, and floats in the order of [`f64::total_cmp`], which
+/// places negative NaNs first, `-0.0` before `0.0`, and positive NaNs last. The result is the
+/// same as [`sort`](crate::newsort::sort) for integers, and as
+/// [`sort_by(|a, b| a.total_cmp(b))`](crate::newsort::sort_by) for floats. This sort is
+/// *O*(*n* \* log(*n*)) worst-case.
+///
+/// # Current implementation
+///
+/// This is the same two stage merge sort as [`sort`](crate::newsort::sort), except for the small
+/// slice stage. Instead of the generic insertion sort, the first 8 (or 16) elements of a small
+/// slice are sorted by a sorting network of branchless compare-exchanges (and a branchless
+/// merge), and only the rest are inserted. The network doesn't keep equal elements in order,
+/// which doesn't matter because equal primitives are indistinguishable.
+///
+/// [`sort`](crate::newsort::sort) doesn't pick these kernels for primitives by itself, since that
+/// would take specialization: they are only used through this function.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [-5, 4, 1, -3, 2];
+///
+/// newsort::sort_primitive(&mut v);
+/// assert!(v == [-5, -3, 1, 2, 4]);
+///
+/// let mut f = [2.5, -0.0, f64::NAN, 0.0, -1.0];
+///
+/// newsort::sort_primitive(&mut f);
+/// assert!(f[..4] == [-1.0, -0.0, 0.0, 2.5] && f[4].is_nan());
+/// assert!(f[1].is_sign_negative());
+/// ```
+#[inline]
+pub fn sort_primitive<T>(v: &mut [T])
+where
+    T: Primitive,
+{
+    merge_sort_with_config::<_, _, _, Network>(
+        v,
+        |a, b| a.lt(b),
+        &SortConfig::tuned::<T>(),
+        &mut (),
+    );
+}
+
+/// Sorts small slices of primitives with sorting networks for the first 8 or 16 elements, followed
+/// by insertion sort, and merges picking each element with a conditional move.
+struct Network;
+
+impl<T> Kernel<*mut T> for Network
+where
+    T: Primitive,
+{
+    #[inline(always)]
+    unsafe fn sort_small<F, S>(
+        v: *mut T,
+        len: usize,
+        sorted: usize,
+        _is_less: &mut F,
+        stats: &mut S,
+    ) where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        let (v, mut sorted) = (unsafe { slice::from_raw_parts_mut(v, len) }, sorted);
+        stats.insertion_run();
+        if v.len() >= 16 && sorted < 16 {
+            if sorted < 8 {
+                sort8(&mut v[..8]);
+            }
+            sort8(&mut v[8..16]);
+            merge8(&mut v[..16]);
+            stats.moves(if sorted < 8 { 64 } else { 48 });
+            sorted = 16;
+        } else if v.len() >= 8 && sorted < 8 {
+            sort8(&mut v[..8]);
+            stats.moves(16);
+            sorted = 8;
+        }
+        for i in sorted.max(1)..v.len() {
+            let x = v[i];
+            let mut j = i;
+            while j > 0 && x.lt(&v[j - 1]) {
+                v[j] = v[j - 1];
+                j -= 1;
+            }
+            v[j] = x;
+            stats.moves(i - j);
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn merge<F, S>(
+        v: *mut T,
+        len: usize,
+        mid: usize,
+        buf: *mut T,
+        is_less: &mut F,
+        stats: &mut S,
+    ) -> usize
+    where
+        F: FnMut(&T, &T) -> bool,
+        S: Recorder,
+    {
+        unsafe { merge(v, len, mid, buf, true, is_less, stats) };
+        len
+    }
+}
+
+/// Sorts `v[..8]` with the optimal 19 comparator network.
+#[inline(always)]
+fn sort8<T: Primitive>(v: &mut [T]) {
+    let mut a = [v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7]];
+    macro_rules! cx {
+        ($(($i: expr, $j: expr)),*) => {
+            $({
+                let (x, y) = (a[$i], a[$j]);
+                let swap = y.lt(&x);
+                a[$i] = if swap { y } else { x };
+                a[$j] = if swap { x } else { y };
+            })*
+        };
+    }
+    cx!((0, 2), (1, 3), (4, 6), (5, 7));
+    cx!((0, 4), (1, 5), (2, 6), (3, 7));
+    cx!((0, 1), (2, 3), (4, 5), (6, 7));
+    cx!((2, 4), (3, 5));
+    cx!((1, 4), (3, 6));
+    cx!((1, 2), (3, 4), (5, 6));
+    v[..8].copy_from_slice(&a);
+}
+
+/// Merges the sorted halves of `v[..16]`, from both ends at once without branches.
+#[inline(always)]
+fn merge8<T: Primitive>(v: &mut [T]) {
+    let mut src = [v[0]; 16];
+    src.copy_from_slice(&v[..16]);
+    let (mut l, mut r) = (0, 8);
+    let (mut l_rev, mut r_rev) = (7_usize, 15_usize);
+    for k in 0..8 {
+        // The front takes the lesser of the next elements, and the back the greater of the last.
+        let right = src[r].lt(&src[l]);
+        v[k] = if right { src[r] } else { src[l] };
+        r += right as usize;
+        l += !right as usize;
+        let left = src[r_rev].lt(&src[l_rev]);
+        v[15 - k] = if left { src[l_rev] } else { src[r_rev] };
+        l_rev = l_rev.wrapping_sub(left as usize);
+        r_rev = r_rev.wrapping_sub(!left as usize);
+    }
+}
@@ -0,0 +1,156 @@
+// newsort as slice methods
+
+use core::cmp::Ordering;
+
+/// Extension trait which makes the [`newsort`](crate::newsort) functions callable as slice methods.
+///
+/// It is implemented for `[T]`, so the methods are also available on `Vec<T>`, `Box<[T]>` and
+/// arrays through auto-deref, the same as the inherent slice sorts.
+///
+/// # Examples
+///
+/// ```
+/// use newsort::NewSortExt;
+///
+/// let mut v = vec![-5i32, 4, 1, -3, 2];
+/// v.new_sort();
+/// assert!(v == [-5, -3, 1, 2, 4]);
+///
+/// v.new_sort_by(|a, b| b.cmp(a));
+/// assert!(v == [4, 2, 1, -3, -5]);
+///
+/// let mut b: Box<[i32]> = v.into_boxed_slice();
+/// b.new_sort_by_key(|k| k.abs());
+/// assert!(*b == [1, 2, -3, 4, -5]);
+/// ```
+pub trait NewSortExt<T> {
+    /// Sorts the slice. See [`newsort::sort`](crate::newsort::sort).
+    fn new_sort(&mut self)
+    where
+        T: Ord;
+
+    /// Sorts the slice with a comparator function.
+    /// See [`newsort::sort_by`](crate::newsort::sort_by).
+    fn new_sort_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering;
+
+    /// Sorts the slice with a key extraction function.
+    /// See [`newsort::sort_by_key`](crate::newsort::sort_by_key).
+    fn new_sort_by_key<K, F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord;
+
+    /// Sorts the slice with a key extraction function, calling it only once per element.
+    /// See [`newsort::sort_by_cached_key`](crate::newsort::sort_by_cached_key).
+    #[cfg(feature = "alloc")]
+    fn new_sort_by_cached_key<K, F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord;
+}
+
+impl<T> NewSortExt<T> for [T] {
+    #[inline]
+    fn new_sort(&mut self)
+    where
+        T: Ord,
+    {
+        crate::newsort::sort(self);
+    }
+
+    #[inline]
+    fn new_sort_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        crate::newsort::sort_by(self, compare);
+    }
+
+    #[inline]
+    fn new_sort_by_key<K, F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        crate::newsort::sort_by_key(self, f);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn new_sort_by_cached_key<K, F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        crate::newsort::sort_by_cached_key(self, f);
+    }
+}
+
+/// Extension trait which makes the [`par_newsort`](crate::par_newsort) functions callable as
+/// slice methods, in the same way as rayon's `par_sort`, `par_sort_by` and `par_sort_by_key`.
+///
+/// # Examples
+///
+/// ```
+/// use newsort::ParNewSortExt;
+///
+/// let mut v: Vec<i32> = (0..10_000).map(|i| (i * 7919) % 10_007).collect();
+/// v.par_new_sort();
+/// assert!(v.windows(2).all(|w| w[0] <= w[1]));
+///
+/// v.par_new_sort_by(|a, b| b.cmp(a));
+/// assert!(v.windows(2).all(|w| w[0] >= w[1]));
+///
+/// v.par_new_sort_by_key(|k| k % 10);
+/// assert!(v.windows(2).all(|w| w[0] % 10 <= w[1] % 10));
+/// ```
+#[cfg(feature = "rayon")]
+pub trait ParNewSortExt<T: Send + Sync> {
+    /// Sorts the slice in parallel. See [`par_newsort::par_sort`](crate::par_newsort::par_sort).
+    fn par_new_sort(&mut self)
+    where
+        T: Ord;
+
+    /// Sorts the slice in parallel with a comparator function.
+    /// See [`par_newsort::par_sort_by`](crate::par_newsort::par_sort_by).
+    fn par_new_sort_by<F>(&mut self, compare: F)
+    where
+        F: Fn(&T, &T) -> Ordering + Sync;
+
+    /// Sorts the slice in parallel with a key extraction function.
+    /// See [`par_newsort::par_sort_by_key`](crate::par_newsort::par_sort_by_key).
+    fn par_new_sort_by_key<K, F>(&mut self, f: F)
+    where
+        K: Ord,
+        F: Fn(&T) -> K + Sync;
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Send + Sync> ParNewSortExt<T> for [T] {
+    #[inline]
+    fn par_new_sort(&mut self)
+    where
+        T: Ord,
+    {
+        crate::par_newsort::par_sort(self);
+    }
+
+    #[inline]
+    fn par_new_sort_by<F>(&mut self, compare: F)
+    where
+        F: Fn(&T, &T) -> Ordering + Sync,
+    {
+        crate::par_newsort::par_sort_by(self, compare);
+    }
+
+    #[inline]
+    fn par_new_sort_by_key<K, F>(&mut self, f: F)
+    where
+        K: Ord,
+        F: Fn(&T) -> K + Sync,
+    {
+        crate::par_newsort::par_sort_by_key(self, f);
+    }
+}
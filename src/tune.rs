@@ -0,0 +1,363 @@
+// newsort-tune: pick the sort thresholds for the current machine
+//
+// For each of i16, i32, i64, i128 and String, sweeps the small slice length, then the insertion
+// sort length, then the parallel chunk size, keeping the fastest of each in turn. The sorts are
+// timed on the benchmark's patterns and variants, like `run_test` in the benchmark.
+//
+// The candidates are passed to `sort_with_config` and `par_sort_with_config` at run time, rather
+// than built into a copy of the kernels each as `do-all-builds` does with the constants. `sort`
+// itself takes its thresholds from `SortConfig::tuned` at run time, so this times the same code
+// the library runs with the chosen values, in one build instead of one per candidate.
+//
+// The results are written as a Rust const file, which the library includes from `src/tuned.rs`:
+//
+//     cargo run --release --features bench --bin newsort-tune -- -o src/tuned.rs
+
+#[allow(dead_code)] // only benchmark data is generated
+mod patterns;
+use ::newsort::{par_sort_with_config, sort_with_config, SortConfig};
+use patterns::{generate_int, FromUsize, Pattern, TestType, Variant};
+use std::fmt::Write;
+use std::mem::size_of;
+use std::ops::RemAssign;
+
+const SMALL_SLICE_LENS: [usize; 10] = [4, 6, 8, 10, 12, 14, 16, 20, 24, 32];
+const MAX_INSERTIONS: [usize; 9] = [8, 12, 16, 20, 24, 32, 40, 48, 64];
+// The parallel chunk size is swept in multiples of the small slice length.
+const PAR_CHUNK_FACTORS: [usize; 5] = [64, 128, 256, 512, 1024];
+
+const PATTERNS: [Pattern; 5] = [
+    Pattern::Sawtooth,
+    Pattern::Rand,
+    Pattern::Stagger,
+    Pattern::Plateau,
+    Pattern::Shuffle,
+];
+const VARIANTS: [Variant; 7] = [
+    Variant::Ident,
+    Variant::Reverse,
+    Variant::ReverseFront,
+    Variant::ReverseBack,
+    Variant::Sorted,
+    Variant::ReverseSorted,
+    Variant::Dither,
+];
+
+#[derive(Copy, Clone)]
+struct Thresholds {
+    small_slice_len: usize,
+    max_insertion: usize,
+    par_chunk_size: usize,
+}
+
+impl Thresholds {
+    fn config(&self) -> SortConfig {
+        SortConfig::new()
+            .small_slice_len(self.small_slice_len)
+            .max_insertion(self.max_insertion)
+            .par_chunk_size(self.par_chunk_size)
+    }
+}
+
+struct Options {
+    max: u32,
+    par_size: u32,
+    max_time_ms: u128,
+    verbose: bool,
+}
+
+// Sorts `v_once` with `cfg` for at least `max_time_ms`, and returns the throughput in M/sec.
+fn run_test<T>(cfg: &SortConfig, parallel: bool, v_once: &[T], max_time_ms: u128) -> f64
+where
+    T: Ord + Clone + Send + Sync,
+{
+    let len = v_once.len();
+    let mut v = v_once.to_vec();
+    let mut trial_count = 0;
+    let start_total = std::time::Instant::now();
+
+    loop {
+        if parallel {
+            par_sort_with_config(&mut v, cfg);
+        } else {
+            sort_with_config(&mut v, cfg);
+        }
+        trial_count += 1;
+        if start_total.elapsed().as_millis() >= max_time_ms {
+            break;
+        }
+        v.clone_from_slice(v_once);
+    }
+    let mut elapsed = start_total.elapsed().as_secs_f64();
+    (0..trial_count - 1).for_each(|_| v.clone_from_slice(v_once));
+    elapsed -= start_total.elapsed().as_secs_f64() - elapsed;
+    (len * trial_count) as f64 / 1_000_000_f64 / elapsed
+}
+
+// Returns the geometric mean throughput of sorting each of `vs` with `cfg`.
+fn score<T>(cfg: &SortConfig, parallel: bool, vs: &[Vec<T>], max_time_ms: u128) -> f64
+where
+    T: Ord + Clone + Send + Sync,
+{
+    let ln_sum: f64 = vs
+        .iter()
+        .map(|v| run_test(cfg, parallel, v, max_time_ms).ln())
+        .sum();
+    (ln_sum / vs.len() as f64).exp()
+}
+
+// Returns the fastest of `candidates`, which are `values` applied to `thresholds` by `set`.
+fn sweep<T>(
+    name: &str,
+    thresholds: Thresholds,
+    values: &[usize],
+    set: fn(&mut Thresholds, usize),
+    parallel: bool,
+    vs: &[Vec<T>],
+    opts: &Options,
+) -> Thresholds
+where
+    T: Ord + Clone + Send + Sync,
+{
+    let mut best = (thresholds, 0.0);
+    for &value in values {
+        let mut candidate = thresholds;
+        set(&mut candidate, value);
+        let throughput = score(&candidate.config(), parallel, vs, opts.max_time_ms);
+        if opts.verbose {
+            eprintln!("{: >20} {: >7} {: >12.1}", name, value, throughput);
+        }
+        if throughput > best.1 {
+            best = (candidate, throughput);
+        }
+    }
+    best.0
+}
+
+// Generates the benchmark's patterns and variants for each decade up to `10^max`, and a few
+// vecs of `10^par_size` for the parallel sort.
+fn generate<T>(opts: &Options) -> (Vec<Vec<T>>, Vec<Vec<T>>)
+where
+    T: FromUsize + Ord + RemAssign,
+{
+    let mut seq = Vec::new();
+    for size_pow in 1..=opts.max {
+        let size = 10_usize.pow(size_pow);
+        for &m in &[2, size] {
+            for &pattern in &PATTERNS {
+                for &variant in &VARIANTS {
+                    seq.push(generate_int(
+                        pattern,
+                        variant,
+                        size,
+                        false,
+                        m,
+                        TestType::BenchMark,
+                        0,
+                    ));
+                }
+            }
+        }
+    }
+    let size = 10_usize.pow(opts.par_size);
+    let mut par = Vec::new();
+    for &pattern in &[Pattern::Sawtooth, Pattern::Rand] {
+        for &variant in &[Variant::Ident, Variant::ReverseFront] {
+            par.push(generate_int(
+                pattern,
+                variant,
+                size,
+                false,
+                size / 5,
+                TestType::BenchMark,
+                0,
+            ));
+        }
+    }
+    (seq, par)
+}
+
+fn tune<T>(name: &str, seq: &[Vec<T>], par: &[Vec<T>], opts: &Options) -> Thresholds
+where
+    T: Ord + Clone + Send + Sync,
+{
+    let default = SortConfig::new();
+    let mut t = Thresholds {
+        small_slice_len: 10,
+        max_insertion: 20,
+        par_chunk_size: 2560,
+    };
+    assert!(
+        t.config() == default,
+        "tuning must start from the default thresholds"
+    );
+
+    eprint!("{}...", name);
+    t = sweep(
+        "small_slice_len",
+        t,
+        &SMALL_SLICE_LENS,
+        |t, n| t.small_slice_len = n,
+        false,
+        seq,
+        opts,
+    );
+    t = sweep(
+        "max_insertion",
+        t,
+        &MAX_INSERTIONS,
+        |t, n| t.max_insertion = n,
+        false,
+        seq,
+        opts,
+    );
+    let par_chunk_sizes: Vec<usize> = PAR_CHUNK_FACTORS
+        .iter()
+        .map(|f| f * t.small_slice_len)
+        .collect();
+    t = sweep(
+        "par_chunk_size",
+        t,
+        &par_chunk_sizes,
+        |t, n| t.par_chunk_size = n,
+        true,
+        par,
+        opts,
+    );
+    eprintln!(
+        " small_slice_len {}, max_insertion {}, par_chunk_size {}",
+        t.small_slice_len, t.max_insertion, t.par_chunk_size
+    );
+    t
+}
+
+fn tune_int<T>(name: &str, opts: &Options) -> (usize, String, Thresholds)
+where
+    T: FromUsize + Ord + RemAssign + Clone + Send + Sync,
+{
+    let (seq, par) = generate::<T>(opts);
+    (
+        size_of::<T>(),
+        name.to_string(),
+        tune(name, &seq, &par, opts),
+    )
+}
+
+fn tune_string(opts: &Options) -> (usize, String, Thresholds) {
+    let (seq, par) = generate::<i64>(opts);
+    let to_strings = |vs: Vec<Vec<i64>>| -> Vec<Vec<String>> {
+        vs.iter()
+            .map(|v| v.iter().map(|x| x.to_string()).collect())
+            .collect()
+    };
+    let (seq, par) = (to_strings(seq), to_strings(par));
+    (
+        size_of::<String>(),
+        "String".to_string(),
+        tune("String", &seq, &par, opts),
+    )
+}
+
+fn const_file(mut results: Vec<(usize, String, Thresholds)>) -> String {
+    results.sort_by_key(|r| r.0);
+    let mut s = String::new();
+    s.push_str("// Generated by newsort-tune, do not edit.\n");
+    s.push_str("//\n");
+    s.push_str("// Regenerate for the current machine with:\n");
+    s.push_str(
+        "//     cargo run --release --features bench --bin newsort-tune -- -o src/tuned.rs\n",
+    );
+    s.push('\n');
+    s.push_str("use crate::newsort::SortConfig;\n");
+    s.push('\n');
+    s.push_str(
+        "/// Thresholds for `SortConfig::tuned`, by element size in bytes in increasing order.\n",
+    );
+    s.push_str("#[rustfmt::skip]\n");
+    writeln!(
+        s,
+        "pub(crate) const TUNED: [(usize, SortConfig); {}] = [",
+        results.len()
+    )
+    .unwrap();
+    for (size, name, t) in &results {
+        writeln!(
+            s,
+            "    ({}, SortConfig::new().small_slice_len({}).max_insertion({}).\
+             par_chunk_size({})), // {}",
+            size, t.small_slice_len, t.max_insertion, t.par_chunk_size, name
+        )
+        .unwrap();
+    }
+    s.push_str("];\n");
+    s
+}
+
+fn main() {
+    let mut opts = Ok(Options {
+        max: 4,
+        par_size: 6,
+        max_time_ms: 5,
+        verbose: false,
+    });
+    let mut out = None;
+    let mut iter = std::env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        let o = if let Ok(o) = &mut opts { o } else { break };
+        match &arg[..] {
+            "-v" | "--verbose" => o.verbose = true,
+            "-o" | "--out" => {
+                if let Some(path) = iter.next() {
+                    out = Some(path);
+                } else {
+                    opts = Err("path not provided");
+                }
+            }
+            "--max" | "--par-size" | "--ms" => {
+                if let Some(n) = iter.next().and_then(|arg2| arg2.parse::<u32>().ok()) {
+                    match &arg[..] {
+                        "--max" => o.max = n,
+                        "--par-size" => o.par_size = n,
+                        _ => o.max_time_ms = n as u128,
+                    }
+                } else {
+                    opts = Err("invalid or missing number");
+                }
+            }
+            _ => opts = Err("unknown option"),
+        }
+    }
+
+    let opts = match opts {
+        Ok(o) if o.max >= 1 && o.par_size >= 1 && o.max_time_ms >= 1 => o,
+        r => {
+            eprintln!("error: {}", r.err().unwrap_or("numbers must be positive"));
+            eprintln!("usage: newsort-tune [ -o file | --out file ] [ -v | --verbose ]");
+            eprintln!("                    [ --max n ] [ --par-size n ] [ --ms n ]");
+            std::process::exit(1);
+        }
+    };
+
+    eprintln!(
+        "Sequential range 10 to {}, parallel size {} with {} cpus, {} ms per sort",
+        10_usize.pow(opts.max),
+        10_usize.pow(opts.par_size),
+        rayon::current_num_threads(),
+        opts.max_time_ms
+    );
+    let results = vec![
+        tune_int::<i16>("i16", &opts),
+        tune_int::<i32>("i32", &opts),
+        tune_int::<i64>("i64", &opts),
+        tune_int::<i128>("i128", &opts),
+        tune_string(&opts),
+    ];
+    let file = const_file(results);
+    if let Some(path) = out {
+        std::fs::write(&path, file).unwrap();
+        eprintln!("wrote {}", path);
+    } else {
+        print!("{}", file);
+    }
+}
@@ -0,0 +1,15 @@
+// Placeholder thresholds: every type has the `SortConfig::new` defaults until this file is
+// generated for the target machine with:
+//     cargo run --release --features bench --bin newsort-tune -- -o src/tuned.rs
+
+use crate::newsort::SortConfig;
+
+/// Thresholds for `SortConfig::tuned`, by element size in bytes in increasing order.
+#[rustfmt::skip]
+pub(crate) const TUNED: [(usize, SortConfig); 5] = [
+    (2, SortConfig::new().small_slice_len(10).max_insertion(20).par_chunk_size(2560)), // i16
+    (4, SortConfig::new().small_slice_len(10).max_insertion(20).par_chunk_size(2560)), // i32
+    (8, SortConfig::new().small_slice_len(10).max_insertion(20).par_chunk_size(2560)), // i64
+    (16, SortConfig::new().small_slice_len(10).max_insertion(20).par_chunk_size(2560)), // i128
+    (24, SortConfig::new().small_slice_len(10).max_insertion(20).par_chunk_size(2560)), // String
+];
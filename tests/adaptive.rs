@@ -0,0 +1,120 @@
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{patterns, Rng};
+
+// Returns `len` keys in `0..range` as ascending and descending runs of random lengths from
+// `min_run` to `max_run`.
+fn multi_run(rng: &mut Rng, len: usize, range: u64, min_run: usize, max_run: usize) -> Vec<u64> {
+    let mut v = Vec::with_capacity(len);
+    while v.len() < len {
+        let n = (min_run + rng.below((max_run - min_run + 1) as u64) as usize).min(len - v.len());
+        let mut run: Vec<u64> = (0..n).map(|_| rng.below(range)).collect();
+        run.sort();
+        if rng.below(3) == 0 {
+            run.reverse();
+        }
+        v.extend(run);
+    }
+    v
+}
+
+// Sorts `(key, index)` pairs by key, so the indices show that the sort is stable.
+fn check(keys: &[u64]) {
+    let mut expected: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    expected.sort_by_key(|p| p.0);
+
+    let mut v: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    newsort::sort_adaptive_by(&mut v, |a, b| a.0.cmp(&b.0));
+    assert_eq!(v, expected);
+
+    let mut v: Vec<String> = keys.iter().map(|k| format!("{k:020}")).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    newsort::sort_adaptive(&mut v);
+    assert_eq!(v, expected);
+}
+
+#[test]
+fn matches_std() {
+    let mut rng = Rng::new(13);
+    for &len in &[16, 100, 1000, 5000] {
+        for &range in &[4, 100, u64::MAX] {
+            for keys in patterns(&mut rng, len, range) {
+                check(&keys);
+            }
+        }
+    }
+    // Many runs of very different lengths, so runs are both merged as they are pushed and left on
+    // the stack for the final merges.
+    for &(min_run, max_run) in &[(1, 8), (1, 100), (20, 300), (200, 2000)] {
+        for &range in &[4, 100, u64::MAX] {
+            check(&multi_run(&mut rng, 5000, range, min_run, max_run));
+        }
+    }
+}
+
+// The runs are merged in the order of powersort, which does at most `n * (H + 2)` comparisons
+// for `H` the entropy of the run lengths. Merging them from left to right would take about
+// `n * runs / 2` here.
+#[test]
+fn merges_in_powersort_order() {
+    let mut rng = Rng::new(14);
+    for &(min_run, max_run) in &[(100, 200), (100, 1000), (500, 3000)] {
+        let keys = multi_run(&mut rng, 20000, u64::MAX, min_run, max_run);
+        let mut lens = Vec::new();
+        let mut start = 0;
+        for i in 1..=keys.len() {
+            if i == keys.len() || (keys[i - 1] < keys[i]) != (keys[start] < keys[start + 1]) {
+                lens.push(i - start);
+                start = i;
+            }
+        }
+        let n = keys.len() as f64;
+        let entropy: f64 = lens
+            .iter()
+            .map(|&l| l as f64 / n * (n / l as f64).log2())
+            .sum();
+
+        let mut v = keys.clone();
+        let mut cmps = 0;
+        newsort::sort_adaptive_by(&mut v, |a, b| {
+            cmps += 1;
+            a.cmp(b)
+        });
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+        // One more pass of comparisons finds the runs.
+        assert!(
+            (cmps as f64) < n * (entropy + 3.0),
+            "{} for {} runs",
+            cmps,
+            lens.len()
+        );
+    }
+}
+
+#[test]
+fn panic_keeps_elements() {
+    let mut rng = Rng::new(15);
+    let keys: Vec<String> = multi_run(&mut rng, 3000, 50, 1, 200)
+        .iter()
+        .map(|k| k.to_string())
+        .collect();
+    for limit in [100, 3000, 12000] {
+        let mut v = keys.clone();
+        let mut calls = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            newsort::sort_adaptive_by(&mut v, |a, b| {
+                calls += 1;
+                assert!(calls < limit);
+                a.cmp(b)
+            })
+        }));
+        assert!(result.is_err());
+        v.sort();
+        let mut expected = keys.clone();
+        expected.sort();
+        assert_eq!(v, expected);
+    }
+}
@@ -0,0 +1,53 @@
+// Helpers shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+/// A xorshift generator, so the tests don't need `rand`.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a value in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Returns `len` values in `0..range`, as a few ascending and descending runs with random values
+/// between them.
+pub fn runs(rng: &mut Rng, len: usize, range: u64) -> Vec<u64> {
+    let mut v: Vec<u64> = (0..len).map(|_| rng.below(range)).collect();
+    let mut i = 0;
+    while i < len {
+        let n = (rng.below(len as u64 / 4 + 1) as usize).min(len - i);
+        let run = &mut v[i..i + n];
+        run.sort();
+        if rng.below(3) == 0 {
+            run.reverse();
+        }
+        i += n + rng.below(8) as usize;
+    }
+    v
+}
+
+/// Returns the patterns the sorts are checked against: random values in `0..range`, runs of them,
+/// and sorted, reversed and sawtooth slices.
+pub fn patterns(rng: &mut Rng, len: usize, range: u64) -> Vec<Vec<u64>> {
+    let random = (0..len).map(|_| rng.below(range)).collect();
+    let runs = runs(rng, len, range);
+    let mut sorted: Vec<u64> = (0..len).map(|_| rng.below(range)).collect();
+    sorted.sort();
+    let mut reversed = sorted.clone();
+    reversed.reverse();
+    let sawtooth = (0..len as u64).map(|i| i % 97 % range).collect();
+    vec![random, runs, sorted, reversed, sawtooth]
+}
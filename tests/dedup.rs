@@ -0,0 +1,80 @@
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{patterns, Rng};
+
+// Deduplicates `(key, index)` pairs by key, so the indices show which of the repeats was kept,
+// and counts the repeats of each key.
+fn check(keys: &[u64]) {
+    let mut expected: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    expected.sort_by_key(|p| p.0);
+    let mut counts = Vec::new();
+    for group in expected.chunk_by(|a, b| a.0 == b.0) {
+        counts.push(group.len());
+    }
+    expected.dedup_by_key(|p| p.0);
+
+    let mut v: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    let removed = newsort::sort_dedup_by_key(&mut v, |p| p.0);
+    assert_eq!(v, expected);
+    assert_eq!(removed, keys.len() - expected.len());
+
+    let mut v: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    assert_eq!(newsort::sort_dedup_counts_by_key(&mut v, |p| p.0), counts);
+    assert_eq!(v, expected);
+
+    let mut v: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+    let mut expected: Vec<String> = v.clone();
+    expected.sort();
+    expected.dedup();
+    newsort::sort_dedup(&mut v);
+    assert_eq!(v, expected);
+}
+
+#[test]
+fn matches_std() {
+    let mut rng = Rng::new(7);
+    for &len in &[16, 100, 500, 1000, 5000] {
+        for &range in &[1, 4, 100, u64::MAX] {
+            for keys in patterns(&mut rng, len, range) {
+                check(&keys);
+            }
+        }
+    }
+}
+
+#[test]
+fn panic_keeps_elements() {
+    let mut rng = Rng::new(8);
+    let keys: Vec<u64> = (0..600).map(|_| rng.below(30)).collect();
+    for limit in [50, 500, 2500] {
+        let mut v: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        let mut calls = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            newsort::sort_dedup_by_key(&mut v, |s| {
+                calls += 1;
+                assert!(calls < limit);
+                s.parse::<u64>().unwrap()
+            })
+        }));
+        assert!(result.is_err());
+        v.sort();
+        let mut expected: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        expected.sort();
+        assert_eq!(v, expected);
+
+        let mut v: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        let mut calls = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            newsort::sort_dedup_counts_by_key(&mut v, |s| {
+                calls += 1;
+                assert!(calls < limit);
+                s.parse::<u64>().unwrap()
+            })
+        }));
+        assert!(result.is_err());
+        v.sort();
+        assert_eq!(v, expected);
+    }
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{patterns, Rng};
+
+// Sorts the keys with their indices as values, so stability shows in the values, and again with
+// `String` values, which must move with their keys.
+fn check(keys: &[u64]) {
+    let mut expected: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    expected.sort_by_key(|p| p.0);
+
+    let (mut k, mut v): (Vec<u64>, Vec<usize>) = (keys.to_vec(), (0..keys.len()).collect());
+    newsort::sort_pairs(&mut k, &mut v);
+    assert!(k
+        .iter()
+        .copied()
+        .zip(v.iter().copied())
+        .eq(expected.iter().copied()));
+
+    let (mut k, mut v): (Vec<u64>, Vec<String>) = (
+        keys.to_vec(),
+        (0..keys.len()).map(|i| i.to_string()).collect(),
+    );
+    newsort::sort_pairs_by(&mut k, &mut v, |a, b| a.cmp(b));
+    let expected: Vec<(u64, String)> = expected.iter().map(|p| (p.0, p.1.to_string())).collect();
+    assert_eq!(k.into_iter().zip(v).collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn matches_std() {
+    let mut rng = Rng::new(5);
+    for &len in &[16, 100, 1000, 5000] {
+        for &range in &[4, 100, u64::MAX] {
+            for keys in patterns(&mut rng, len, range) {
+                check(&keys);
+            }
+        }
+    }
+}
+
+#[test]
+fn panic_keeps_pairs() {
+    let mut rng = Rng::new(6);
+    let keys: Vec<u64> = (0..2000).map(|_| rng.below(50)).collect();
+    let vals: Vec<String> = keys
+        .iter()
+        .zip(0..)
+        .map(|(k, i)| format!("{k}/{i}"))
+        .collect();
+    for limit in [100, 5000, 15000] {
+        let (mut k, mut v) = (keys.clone(), vals.clone());
+        let mut calls = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            newsort::sort_pairs_by(&mut k, &mut v, |a, b| {
+                calls += 1;
+                assert!(calls < limit);
+                a.cmp(b)
+            })
+        }));
+        assert!(result.is_err());
+        // Every value is still next to its key.
+        for (key, val) in k.iter().zip(&v) {
+            assert!(val.starts_with(&format!("{key}/")));
+        }
+        v.sort();
+        let mut expected = vals.clone();
+        expected.sort();
+        assert_eq!(v, expected);
+    }
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{patterns, Rng};
+
+// Sorts `(key, index)` pairs by key, so the indices show that the prefix is stable, and checks
+// that the rest of the slice still holds the other elements.
+fn check(keys: &[u64], k: usize) {
+    let mut expected: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    expected.sort_by_key(|p| p.0);
+    let n = k.min(keys.len());
+
+    let mut v: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    newsort::partial_sort_by(&mut v, k, |a, b| a.0.cmp(&b.0));
+    assert_eq!(v[..n], expected[..n]);
+    v.sort_by_key(|p| p.1);
+    assert!(v.iter().map(|p| p.0).eq(keys.iter().copied()));
+
+    let v: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    let top = newsort::select_top_k_stable_by(&v, k, |a, b| a.0.cmp(&b.0));
+    assert_eq!(top, expected[..n]);
+
+    let mut v: Vec<String> = keys.iter().map(|k| format!("{k:020}")).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    newsort::partial_sort(&mut v, k);
+    assert_eq!(v[..n], expected[..n]);
+    v.sort();
+    assert_eq!(v, expected);
+}
+
+#[test]
+fn matches_std() {
+    let mut rng = Rng::new(9);
+    for &len in &[16, 100, 1000, 5000] {
+        for &range in &[4, 100, u64::MAX] {
+            for keys in patterns(&mut rng, len, range) {
+                for k in [0, 1, 7, 40, len / 3, len / 2 + 1, len - 1, len, len + 1] {
+                    check(&keys, k);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn panic_keeps_elements() {
+    let mut rng = Rng::new(10);
+    let keys: Vec<String> = (0..2000).map(|_| rng.below(50).to_string()).collect();
+    for limit in [100, 2000, 6000] {
+        let mut v = keys.clone();
+        let mut calls = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            newsort::partial_sort_by(&mut v, 100, |a, b| {
+                calls += 1;
+                assert!(calls < limit);
+                a.cmp(b)
+            })
+        }));
+        assert!(result.is_err());
+        v.sort();
+        let mut expected = keys.clone();
+        expected.sort();
+        assert_eq!(v, expected);
+    }
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{patterns, Rng};
+
+// Every length up to 64 reaches both networks with each sorted prefix, and the longer ones merge.
+fn lens() -> impl Iterator<Item = usize> {
+    (0..=64).chain([100, 1000, 5000, 20000])
+}
+
+fn check_ints<T>(keys: &[u64], to: impl Fn(u64) -> T)
+where
+    T: newsort::Primitive + Ord + core::fmt::Debug,
+{
+    let mut v: Vec<T> = keys.iter().map(|&k| to(k)).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    newsort::sort_primitive(&mut v);
+    assert_eq!(v, expected);
+}
+
+#[test]
+fn ints_match_std() {
+    let mut rng = Rng::new(11);
+    for len in lens() {
+        for &range in &[2, 100, u64::MAX] {
+            for keys in patterns(&mut rng, len, range) {
+                check_ints(&keys, |k| k as i8);
+                check_ints(&keys, |k| k as u16);
+                check_ints(&keys, |k| k as i32);
+                check_ints(&keys, |k| k);
+                check_ints(&keys, |k| (k as i128) << 64 | k as i128);
+            }
+        }
+    }
+}
+
+// Floats are compared by their bits, so `-0.0` and `0.0`, and NaNs of either sign and payload,
+// must each end up exactly where `total_cmp` puts them.
+fn specials() -> [f64; 10] {
+    [
+        f64::NAN,
+        -f64::NAN,
+        f64::from_bits(f64::NAN.to_bits() | 1),
+        0.0,
+        -0.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::MIN_POSITIVE / 2.0,
+        -f64::MIN_POSITIVE / 2.0,
+        1.0,
+    ]
+}
+
+fn bits<T: Copy, B>(v: &[T], to_bits: fn(T) -> B) -> Vec<B> {
+    v.iter().map(|&x| to_bits(x)).collect()
+}
+
+fn check_floats(v: &[f64]) {
+    let mut expected = v.to_vec();
+    expected.sort_by(|a, b| a.total_cmp(b));
+    let mut w = v.to_vec();
+    newsort::sort_primitive(&mut w);
+    assert_eq!(bits(&w, f64::to_bits), bits(&expected, f64::to_bits));
+
+    let mut expected: Vec<f32> = v.iter().map(|&x| x as f32).collect();
+    let mut w = expected.clone();
+    expected.sort_by(|a, b| a.total_cmp(b));
+    newsort::sort_primitive(&mut w);
+    assert_eq!(bits(&w, f32::to_bits), bits(&expected, f32::to_bits));
+}
+
+#[test]
+fn floats_match_total_cmp() {
+    let mut rng = Rng::new(12);
+    let specials = specials();
+    for len in lens() {
+        for &range in &[4, 100, u64::MAX] {
+            for keys in patterns(&mut rng, len, range) {
+                // Mostly small values with repeats, mixed with the special values.
+                let v: Vec<f64> = keys
+                    .iter()
+                    .map(|&k| match k % 4 {
+                        0 => specials[(k / 4 % 10) as usize],
+                        _ => (k % 64) as f64 - 32.0,
+                    })
+                    .collect();
+                check_floats(&v);
+            }
+        }
+        let v: Vec<f64> = (0..len).map(|_| f64::from_bits(rng.next())).collect();
+        check_floats(&v);
+    }
+}
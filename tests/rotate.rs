@@ -0,0 +1,69 @@
+// Slices whose sorted halves are in reverse order have the halves swapped rather than merged,
+// through the buffer or by rotating in place.
+
+mod common;
+
+use common::Rng;
+
+// Returns `len` keys as ascending blocks in descending order, with the sorted halves of every
+// level of the merge sort's recursion in reverse order. Keys repeat within blocks but not
+// across them.
+fn reversed_blocks(rng: &mut Rng, len: usize) -> Vec<u64> {
+    let mut v: Vec<u64> = (0..len).map(|_| rng.below(len as u64 / 4)).collect();
+    v.sort();
+    let mut blocks: Vec<&[u64]> = Vec::new();
+    let mut i = 0;
+    while i < len {
+        let n = (1 + rng.below(len as u64 / 8) as usize).min(len - i);
+        let mut end = i + n;
+        // Don't split equal keys between blocks, which would make the halves overlap.
+        while end < len && v[end] == v[end - 1] {
+            end += 1;
+        }
+        blocks.push(&v[i..end]);
+        i = end;
+    }
+    blocks.reverse();
+    blocks.concat()
+}
+
+// Sorts `v` by `key` with and without a buffer, checking the result against the std sort.
+fn check<T, K>(v: &[T], key: K)
+where
+    T: Clone + core::fmt::Debug + PartialEq,
+    K: Fn(&T) -> u64 + Copy,
+{
+    let mut expected = v.to_vec();
+    expected.sort_by_key(key);
+
+    let mut sorted = v.to_vec();
+    newsort::sort_by_key(&mut sorted, key);
+    assert_eq!(sorted, expected);
+
+    let mut sorted = v.to_vec();
+    newsort::sort_in_place_by(&mut sorted, |a, b| key(a).cmp(&key(b)));
+    assert_eq!(sorted, expected);
+}
+
+#[test]
+fn swaps_reversed_halves() {
+    let mut rng = Rng::new(5);
+    for &len in &[30, 100, 500, 2000] {
+        let keys = reversed_blocks(&mut rng, len);
+        // Up to 16 bytes, rotated by reversals when sorting in place. The indices show
+        // stability.
+        let pairs: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+        check(&pairs, |p| p.0);
+        // Larger than `MAX_BUFFERED_SWAP_SIZE`, rotated along cycles even with a buffer.
+        let large: Vec<[u64; 16]> = pairs
+            .iter()
+            .map(|&(k, i)| {
+                let mut e = [0; 16];
+                e[0] = k;
+                e[1] = i as u64;
+                e
+            })
+            .collect();
+        check(&large, |e| e[0]);
+    }
+}
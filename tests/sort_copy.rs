@@ -0,0 +1,54 @@
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::{patterns, Rng};
+
+// Sorts `(key, index)` pairs by key, so stability shows in the indices.
+fn check(keys: &[u64]) {
+    let mut v: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    let mut expected = v.clone();
+    expected.sort_by_key(|p| p.0);
+    newsort::sort_copy_by(&mut v, |a, b| a.0.cmp(&b.0));
+    assert_eq!(v, expected);
+
+    let mut v: Vec<u32> = keys.iter().map(|&k| k as u32).collect();
+    let mut expected = v.clone();
+    expected.sort();
+    newsort::sort_copy(&mut v);
+    assert_eq!(v, expected);
+}
+
+#[test]
+fn matches_std() {
+    let mut rng = Rng::new(1);
+    for &len in &[16, 100, 1000, 5000] {
+        for &range in &[4, 100, u64::MAX] {
+            for keys in patterns(&mut rng, len, range) {
+                check(&keys);
+            }
+        }
+    }
+}
+
+#[test]
+fn panic_keeps_elements() {
+    let mut rng = Rng::new(2);
+    let keys: Vec<u64> = (0..2000).map(|_| rng.below(50)).collect();
+    for limit in [100, 5000, 15000] {
+        let mut v = keys.clone();
+        let mut calls = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            newsort::sort_copy_by(&mut v, |a, b| {
+                calls += 1;
+                assert!(calls < limit);
+                a.cmp(b)
+            })
+        }));
+        assert!(result.is_err());
+        v.sort();
+        let mut expected = keys.clone();
+        expected.sort();
+        assert_eq!(v, expected);
+    }
+}